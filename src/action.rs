@@ -1,5 +1,9 @@
 // action.rs
 
+use std::fmt;
+
+use export::ExportFormat;
+
 pub enum SudokuAction {
     NoOp,
     Quit,
@@ -7,6 +11,15 @@ pub enum SudokuAction {
     // New(filename)
     New(Option<String>),
 
+    // NewFromString(contents)
+    NewFromString(String),
+
+    NextPuzzle,
+    PrevPuzzle,
+
+    // GotoPuzzle(index)
+    GotoPuzzle(usize),
+
     Undo,
     Redo,
 
@@ -19,5 +32,371 @@ pub enum SudokuAction {
     // CrossOutValue(x,y,v)
     CrossOutValue(u8,u8,u8),
 
+    // ToggleCrossOut(x,y,v), crosses out v or restores it
+    ToggleCrossOut(u8,u8,u8),
+
+    // Actions on several tiles at once, undone in one step.
+
+    // CrossOutMany(tiles,v)
+    CrossOutMany(Vec<(u8,u8)>,u8),
+
+    // ToggleCrossOutMany(tiles,v), crosses out v wherever it can, or
+    // restores it if it is crossed out everywhere
+    ToggleCrossOutMany(Vec<(u8,u8)>,u8),
+
+    // UnassignMany(tiles)
+    UnassignMany(Vec<(u8,u8)>),
+
     AutoFill,
+
+    // SetAutoFillAfterMove(on), from the settings, logged so that a
+    // replay makes the same moves
+    SetAutoFillAfterMove(bool),
+
+    // ColourTile(x,y,colour), clears the colour if already painted with it
+    ColourTile(u8,u8,u8),
+
+    // ColourCandidate(x,y,v,colour), colours the tile instead if v is not
+    // shown there
+    ColourCandidate(u8,u8,u8,u8),
+
+    ClearColours,
+
+    // ToggleLink((x,y,v),(x,y,v),strong), adds a link between two
+    // candidates, changes its kind, or removes it
+    ToggleLink((u8,u8,u8),(u8,u8,u8),bool),
+
+    ClearLinks,
+
+    // mistakes are checked against the puzzle's unique solution
+    ToggleCheckMode,
+    CheckNow,
+    RewindToCorrect,
+
+    TogglePause,
+    Pause,
+
+    // dismiss the completion screen and keep playing
+    Continue,
+
+    SaveGame,
+    LoadGame,
+
+    // LoadFromString(contents), a save game as it was loaded, so that a
+    // replay does not depend on the save game file
+    LoadFromString(String),
+
+    Export,
+
+    // Copy(format)
+    Copy(ExportFormat),
+}
+
+// Actions are written as a name followed by their arguments, separated by
+// spaces, e.g. "assign 3 4 5".  Lists of tiles are written last, as x,y
+// pairs.  Strings are written last, with newlines and backslashes escaped.
+impl fmt::Display for SudokuAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SudokuAction::NoOp => write!(f, "noop"),
+            SudokuAction::Quit => write!(f, "quit"),
+            SudokuAction::New(None) => write!(f, "new"),
+            SudokuAction::New(Some(ref filename)) =>
+                write!(f, "new-file {}", escape(filename)),
+            SudokuAction::NewFromString(ref contents) =>
+                write!(f, "new-from-string {}", escape(contents)),
+            SudokuAction::NextPuzzle => write!(f, "next-puzzle"),
+            SudokuAction::PrevPuzzle => write!(f, "prev-puzzle"),
+            SudokuAction::GotoPuzzle(n) => write!(f, "goto-puzzle {}", n),
+            SudokuAction::Undo => write!(f, "undo"),
+            SudokuAction::Redo => write!(f, "redo"),
+            SudokuAction::AssignValue(x,y,v) => write!(f, "assign {} {} {}", x, y, v),
+            SudokuAction::UnassignValue(x,y) => write!(f, "unassign {} {}", x, y),
+            SudokuAction::CrossOutValue(x,y,v) => write!(f, "cross-out {} {} {}", x, y, v),
+            SudokuAction::ToggleCrossOut(x,y,v) =>
+                write!(f, "toggle-cross-out {} {} {}", x, y, v),
+            SudokuAction::CrossOutMany(ref tiles, v) =>
+                write!(f, "cross-out-many {}{}", v, format_tiles(tiles)),
+            SudokuAction::ToggleCrossOutMany(ref tiles, v) =>
+                write!(f, "toggle-cross-out-many {}{}", v, format_tiles(tiles)),
+            SudokuAction::UnassignMany(ref tiles) =>
+                write!(f, "unassign-many{}", format_tiles(tiles)),
+            SudokuAction::AutoFill => write!(f, "autofill"),
+            SudokuAction::SetAutoFillAfterMove(on) =>
+                write!(f, "autofill-after-move {}", if on { "on" } else { "off" }),
+            SudokuAction::ColourTile(x,y,c) => write!(f, "colour {} {} {}", x, y, c),
+            SudokuAction::ColourCandidate(x,y,v,c) =>
+                write!(f, "colour-candidate {} {} {} {}", x, y, v, c),
+            SudokuAction::ClearColours => write!(f, "clear-colours"),
+            SudokuAction::ToggleLink((x1,y1,v1),(x2,y2,v2),strong) =>
+                write!(f, "toggle-link {} {} {} {} {} {} {}",
+                        if strong { "strong" } else { "weak" }, x1, y1, v1, x2, y2, v2),
+            SudokuAction::ClearLinks => write!(f, "clear-links"),
+            SudokuAction::ToggleCheckMode => write!(f, "toggle-check"),
+            SudokuAction::CheckNow => write!(f, "check"),
+            SudokuAction::RewindToCorrect => write!(f, "rewind"),
+            SudokuAction::TogglePause => write!(f, "toggle-pause"),
+            SudokuAction::Pause => write!(f, "pause"),
+            SudokuAction::Continue => write!(f, "continue"),
+            SudokuAction::SaveGame => write!(f, "save"),
+            SudokuAction::LoadGame => write!(f, "load"),
+            SudokuAction::LoadFromString(ref contents) =>
+                write!(f, "load-from-string {}", escape(contents)),
+            SudokuAction::Export => write!(f, "export"),
+            SudokuAction::Copy(format) => write!(f, "copy {}", format.name())
+        }
+    }
+}
+
+impl SudokuAction {
+    pub fn parse(s: &str) -> Option<SudokuAction> {
+        let (name, rest) = match s.find(' ') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, "")
+        };
+        let args: Vec<&str> = rest.split(' ').collect();
+        let num = |i: usize| args.get(i).and_then(|a| a.parse::<u8>().ok());
+
+        match name {
+            "noop" => Some(SudokuAction::NoOp),
+            "quit" => Some(SudokuAction::Quit),
+            "new" => Some(SudokuAction::New(None)),
+            "new-file" => Some(SudokuAction::New(Some(unescape(rest)))),
+            "new-from-string" => Some(SudokuAction::NewFromString(unescape(rest))),
+            "next-puzzle" => Some(SudokuAction::NextPuzzle),
+            "prev-puzzle" => Some(SudokuAction::PrevPuzzle),
+            "goto-puzzle" => rest.parse::<usize>().ok().map(SudokuAction::GotoPuzzle),
+            "undo" => Some(SudokuAction::Undo),
+            "redo" => Some(SudokuAction::Redo),
+
+            "assign" =>
+                match (num(0), num(1), num(2)) {
+                    (Some(x), Some(y), Some(v)) => Some(SudokuAction::AssignValue(x, y, v)),
+                    _ => None
+                },
+
+            "unassign" =>
+                match (num(0), num(1)) {
+                    (Some(x), Some(y)) => Some(SudokuAction::UnassignValue(x, y)),
+                    _ => None
+                },
+
+            "cross-out" =>
+                match (num(0), num(1), num(2)) {
+                    (Some(x), Some(y), Some(v)) => Some(SudokuAction::CrossOutValue(x, y, v)),
+                    _ => None
+                },
+
+            "toggle-cross-out" =>
+                match (num(0), num(1), num(2)) {
+                    (Some(x), Some(y), Some(v)) => Some(SudokuAction::ToggleCrossOut(x, y, v)),
+                    _ => None
+                },
+
+            "cross-out-many" =>
+                match (num(0), parse_tiles(&args[1..])) {
+                    (Some(v), Some(tiles)) => Some(SudokuAction::CrossOutMany(tiles, v)),
+                    _ => None
+                },
+
+            "toggle-cross-out-many" =>
+                match (num(0), parse_tiles(&args[1..])) {
+                    (Some(v), Some(tiles)) => Some(SudokuAction::ToggleCrossOutMany(tiles, v)),
+                    _ => None
+                },
+
+            "unassign-many" => parse_tiles(&args).map(SudokuAction::UnassignMany),
+
+            "autofill" => Some(SudokuAction::AutoFill),
+
+            "autofill-after-move" =>
+                match rest {
+                    "on" => Some(SudokuAction::SetAutoFillAfterMove(true)),
+                    "off" => Some(SudokuAction::SetAutoFillAfterMove(false)),
+                    _ => None
+                },
+
+            "colour" =>
+                match (num(0), num(1), num(2)) {
+                    (Some(x), Some(y), Some(c)) => Some(SudokuAction::ColourTile(x, y, c)),
+                    _ => None
+                },
+
+            "colour-candidate" =>
+                match (num(0), num(1), num(2), num(3)) {
+                    (Some(x), Some(y), Some(v), Some(c)) =>
+                        Some(SudokuAction::ColourCandidate(x, y, v, c)),
+                    _ => None
+                },
+
+            "clear-colours" => Some(SudokuAction::ClearColours),
+
+            "toggle-link" => {
+                let strong = match args[0] {
+                    "strong" => true,
+                    "weak" => false,
+                    _ => return None
+                };
+                match (num(1), num(2), num(3), num(4), num(5), num(6)) {
+                    (Some(x1), Some(y1), Some(v1), Some(x2), Some(y2), Some(v2)) =>
+                        Some(SudokuAction::ToggleLink((x1, y1, v1), (x2, y2, v2), strong)),
+                    _ => None
+                }
+            },
+
+            "clear-links" => Some(SudokuAction::ClearLinks),
+            "toggle-check" => Some(SudokuAction::ToggleCheckMode),
+            "check" => Some(SudokuAction::CheckNow),
+            "rewind" => Some(SudokuAction::RewindToCorrect),
+            "toggle-pause" => Some(SudokuAction::TogglePause),
+            "pause" => Some(SudokuAction::Pause),
+            "continue" => Some(SudokuAction::Continue),
+            "save" => Some(SudokuAction::SaveGame),
+            "load" => Some(SudokuAction::LoadGame),
+            "load-from-string" => Some(SudokuAction::LoadFromString(unescape(rest))),
+            "export" => Some(SudokuAction::Export),
+            "copy" => ExportFormat::from_name(rest).map(SudokuAction::Copy),
+            _ => None
+        }
+    }
+}
+
+// " x,y x,y ..."
+fn format_tiles(tiles: &Vec<(u8,u8)>) -> String {
+    tiles.iter().map(|&(x, y)| format!(" {},{}", x, y)).collect()
+}
+
+fn parse_tiles(args: &[&str]) -> Option<Vec<(u8,u8)>> {
+    args.iter().filter(|a| !a.is_empty()).map(|a| {
+            let mut xy = a.split(',').map(|n| n.parse::<u8>().ok());
+            match (xy.next(), xy.next(), xy.next()) {
+                (Some(Some(x)), Some(Some(y)), None) => Some((x, y)),
+                _ => None
+            }
+        }).collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SudokuAction;
+    use export::ExportFormat;
+
+    // Every variant, with awkward strings and lists of tiles.
+    fn all_actions() -> Vec<SudokuAction> {
+        vec![SudokuAction::NoOp,
+             SudokuAction::Quit,
+             SudokuAction::New(None),
+             SudokuAction::New(Some(String::from("C:\\puzzles\\my file.txt"))),
+             SudokuAction::NewFromString(String::from("..3|.2.\r\n9..\\n\\\\x\n\n# end \\")),
+             SudokuAction::NewFromString(String::new()),
+             SudokuAction::NextPuzzle,
+             SudokuAction::PrevPuzzle,
+             SudokuAction::GotoPuzzle(1234),
+             SudokuAction::Undo,
+             SudokuAction::Redo,
+             SudokuAction::AssignValue(0, 8, 9),
+             SudokuAction::UnassignValue(8, 0),
+             SudokuAction::CrossOutValue(1, 2, 3),
+             SudokuAction::ToggleCrossOut(4, 5, 6),
+             SudokuAction::CrossOutMany(vec![(0, 0), (8, 8), (3, 4)], 7),
+             SudokuAction::CrossOutMany(Vec::new(), 1),
+             SudokuAction::ToggleCrossOutMany(vec![(2, 2), (2, 3)], 5),
+             SudokuAction::UnassignMany(vec![(1, 1), (7, 6)]),
+             SudokuAction::UnassignMany(Vec::new()),
+             SudokuAction::AutoFill,
+             SudokuAction::SetAutoFillAfterMove(true),
+             SudokuAction::SetAutoFillAfterMove(false),
+             SudokuAction::ColourTile(3, 3, 6),
+             SudokuAction::ColourCandidate(4, 4, 2, 1),
+             SudokuAction::ClearColours,
+             SudokuAction::ToggleLink((0, 0, 1), (0, 5, 1), true),
+             SudokuAction::ToggleLink((1, 2, 3), (4, 5, 6), false),
+             SudokuAction::ClearLinks,
+             SudokuAction::ToggleCheckMode,
+             SudokuAction::CheckNow,
+             SudokuAction::RewindToCorrect,
+             SudokuAction::TogglePause,
+             SudokuAction::Pause,
+             SudokuAction::Continue,
+             SudokuAction::SaveGame,
+             SudokuAction::LoadGame,
+             SudokuAction::LoadFromString(String::from("# save\ngivens ..3\nelapsed 5\n")),
+             SudokuAction::Export,
+             SudokuAction::Copy(ExportFormat::Grid),
+             SudokuAction::Copy(ExportFormat::Line),
+             SudokuAction::Copy(ExportFormat::PencilMarks)]
+    }
+
+    #[test]
+    fn round_trip() {
+        for a in all_actions() {
+            let line = format!("{}", a);
+            assert!(!line.contains('\n') && !line.contains('\r'), "{}", line);
+
+            match SudokuAction::parse(&line) {
+                Some(b) => assert_eq!(format!("{}", b), line),
+                None => panic!("not parsed: {}", line)
+            }
+        }
+    }
+
+    #[test]
+    fn strings_are_kept() {
+        for a in all_actions() {
+            let parsed = SudokuAction::parse(&format!("{}", a));
+            match (a, parsed) {
+                (SudokuAction::NewFromString(s1), Some(SudokuAction::NewFromString(s2)))
+                | (SudokuAction::New(Some(s1)), Some(SudokuAction::New(Some(s2))))
+                | (SudokuAction::LoadFromString(s1), Some(SudokuAction::LoadFromString(s2))) =>
+                    assert_eq!(s1, s2),
+                (SudokuAction::NewFromString(_), _) | (SudokuAction::New(Some(_)), _)
+                | (SudokuAction::LoadFromString(_), _) =>
+                    panic!("not parsed back"),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_are_kept() {
+        match SudokuAction::parse("toggle-cross-out-many 5 2,2 2,3") {
+            Some(SudokuAction::ToggleCrossOutMany(tiles, 5)) =>
+                assert_eq!(tiles, vec![(2, 2), (2, 3)]),
+            _ => panic!("toggle-cross-out-many")
+        }
+    }
+
+    #[test]
+    fn bad_lines() {
+        for line in ["", "assign 1 2", "assign 1 2 x", "unassign-many 1,2,3", "cross-out-many x 1,1",
+                "toggle-link both 0 0 1 0 5 1", "autofill-after-move maybe", "copy svg",
+                "goto-puzzle -1", "dance"].iter() {
+            assert!(SudokuAction::parse(line).is_none(), "{}", line);
+        }
+    }
 }
@@ -0,0 +1,218 @@
+// actionlog.rs
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::{BufReader,LineWriter};
+use std::time::{Instant,SystemTime,UNIX_EPOCH};
+
+use action::SudokuAction;
+use board::Board;
+use frontend::Frontend;
+use game::GameStatus;
+use savegame;
+
+// A log holds one action per line, prefixed with the milliseconds since
+// recording started, e.g.
+//
+//   # play_sudoku action log, started at 1497000000
+//   0 new-from-string .........\n.....3.85\n...
+//   1532 assign 3 4 5
+//
+// Lines starting with '#' are comments.
+
+// (milliseconds, action)
+pub type LogEntry = (u64, SudokuAction);
+
+// Records the actions of another frontend.
+pub struct Recorder<F: Frontend> {
+    frontend: F,
+    log: LineWriter<File>,
+    start: Instant
+}
+
+// Feeds the actions in a log to the game, then hands over to another
+// frontend.  The other frontend is only used for input to quit while
+// the log is being replayed.
+pub struct Replay<F: Frontend> {
+    frontend: F,
+    actions: VecDeque<LogEntry>,
+    start: Instant,
+    fast: bool
+}
+
+impl<F: Frontend> Recorder<F> {
+    pub fn new(frontend: F, filename: &String) -> io::Result<Recorder<F>> {
+        let mut log = LineWriter::new(File::create(filename)?);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs()).unwrap_or(0);
+
+        writeln!(log, "# play_sudoku action log, started at {}", now)?;
+
+        Ok(Recorder {
+            frontend: frontend,
+            log: log,
+            start: Instant::now()
+        })
+    }
+
+    pub fn record(&mut self, action: &SudokuAction) {
+        let ms = elapsed_ms(self.start);
+
+        // log the contents of puzzle files and save games, so the log can
+        // be replayed without them.
+        let res = match *action {
+            SudokuAction::New(Some(ref filename)) =>
+                match read_file(filename) {
+                    Some(contents) =>
+                        writeln!(self.log, "{} {}", ms, SudokuAction::NewFromString(contents)),
+                    None => writeln!(self.log, "{} {}", ms, action)
+                },
+
+            SudokuAction::LoadGame =>
+                match savegame::read() {
+                    Ok(contents) =>
+                        writeln!(self.log, "{} {}", ms, SudokuAction::LoadFromString(contents)),
+                    Err(_) => writeln!(self.log, "{} {}", ms, action)
+                },
+
+            _ => writeln!(self.log, "{} {}", ms, action)
+        };
+
+        if let Err(e) = res {
+            eprintln!("action log: {}", e);
+        }
+    }
+}
+
+impl<F: Frontend> Frontend for Recorder<F> {
+    fn read_input(&mut self) -> SudokuAction {
+        let action = self.frontend.read_input();
+
+        match action {
+            SudokuAction::NoOp => {},
+            _ => self.record(&action)
+        }
+
+        action
+    }
+
+    fn present(&mut self, board: &Board) {
+        self.frontend.present(board);
+    }
+
+    fn on_action(&mut self, action: &SudokuAction) {
+        self.frontend.on_action(action);
+    }
+
+    fn set_status(&mut self, status: &GameStatus) {
+        self.frontend.set_status(status);
+    }
+
+    fn set_clipboard_text(&mut self, text: &str) {
+        self.frontend.set_clipboard_text(text);
+    }
+
+    fn print(&mut self, text: &str) {
+        self.frontend.print(text);
+    }
+}
+
+impl<F: Frontend> Replay<F> {
+    pub fn new(frontend: F, actions: Vec<LogEntry>, fast: bool) -> Replay<F> {
+        Replay {
+            frontend: frontend,
+            actions: actions.into_iter().collect(),
+            start: Instant::now(),
+            fast: fast
+        }
+    }
+}
+
+impl<F: Frontend> Frontend for Replay<F> {
+    fn read_input(&mut self) -> SudokuAction {
+        let due = match self.actions.front() {
+            Some(&(ms, _)) => self.fast || elapsed_ms(self.start) >= ms,
+            None => false
+        };
+
+        if due {
+            return self.actions.pop_front().unwrap().1;
+        }
+
+        match self.frontend.read_input() {
+            SudokuAction::Quit => SudokuAction::Quit,
+            a => if self.actions.is_empty() { a } else { SudokuAction::NoOp }
+        }
+    }
+
+    fn present(&mut self, board: &Board) {
+        self.frontend.present(board);
+    }
+
+    fn on_action(&mut self, action: &SudokuAction) {
+        self.frontend.on_action(action);
+    }
+
+    fn set_status(&mut self, status: &GameStatus) {
+        self.frontend.set_status(status);
+    }
+
+    fn set_clipboard_text(&mut self, text: &str) {
+        self.frontend.set_clipboard_text(text);
+    }
+
+    fn print(&mut self, text: &str) {
+        self.frontend.print(text);
+    }
+}
+
+pub fn load(filename: &String) -> io::Result<Vec<LogEntry>> {
+    let f = BufReader::new(File::open(filename)?);
+    let mut actions = Vec::new();
+
+    for (n, line) in f.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = line.find(' ').and_then(|i| {
+                let ms = line[..i].parse::<u64>().ok();
+                let action = SudokuAction::parse(&line[i + 1..]);
+                ms.and_then(|ms| action.map(|a| (ms, a)))
+            });
+
+        match entry {
+            // quitting ends the recording, and copying would overwrite the
+            // clipboard; neither changes the board.  Saving and loading
+            // would use the player's save game, and a save game that was
+            // loaded is logged with its contents instead.
+            Some((_, SudokuAction::Quit))
+            | Some((_, SudokuAction::Copy(_)))
+            | Some((_, SudokuAction::SaveGame))
+            | Some((_, SudokuAction::LoadGame)) => {},
+
+            Some(e) => actions.push(e),
+
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("line {}: not an action", n + 1)))
+        }
+    }
+
+    Ok(actions)
+}
+
+fn read_file(filename: &String) -> Option<String> {
+    let mut buf = Vec::new();
+    match File::open(filename).and_then(|mut f| f.read_to_end(&mut buf)) {
+        Ok(_) => Some(String::from_utf8_lossy(&buf).into_owned()),
+        Err(_) => None
+    }
+}
+
+fn elapsed_ms(start: Instant) -> u64 {
+    let d = start.elapsed();
+    d.as_secs() * 1000 + (d.subsec_nanos() / 1_000_000) as u64
+}
@@ -0,0 +1,81 @@
+// anim.rs
+
+// Short animations over the board, each a multi-frame FLIC looked for like
+// sudoku.flc: pop.flc when a number is assigned, shake.flc when it
+// conflicts, sweep.flc along a row, column or block that is completed,
+// and win.flc over the whole board when the puzzle is solved.  Frames are
+// drawn centred on their tile or the board, at the board scale, with
+// colour 0 transparent.
+
+use board::Change;
+
+#[derive(Clone,Copy,Eq,Hash,PartialEq)]
+pub enum AnimKind {
+    Pop,
+    Shake,
+    Sweep,
+    Win
+}
+
+pub const ANIM_KINDS: [AnimKind; 4] =
+    [AnimKind::Pop, AnimKind::Shake, AnimKind::Sweep, AnimKind::Win];
+
+// how far behind each tile of a sweep starts from the one before
+const SWEEP_DELAY_MS: u32 = 40;
+
+#[derive(Clone,Copy)]
+pub enum Target {
+    Tile(u8,u8),
+    Board
+}
+
+pub struct Playing {
+    pub kind: AnimKind,
+    pub target: Target,
+
+    // in timer ticks, which may be after now
+    pub start: u32
+}
+
+impl AnimKind {
+    pub fn file_name(&self) -> &'static str {
+        match *self {
+            AnimKind::Pop => "pop.flc",
+            AnimKind::Shake => "shake.flc",
+            AnimKind::Sweep => "sweep.flc",
+            AnimKind::Win => "win.flc"
+        }
+    }
+}
+
+// The animations for the changes to a board, starting at now.
+pub fn from_changes(changes: &[Change], now: u32) -> Vec<Playing> {
+    let mut anims = Vec::new();
+
+    for &c in changes.iter() {
+        match c {
+            Change::Assigned(x, y) =>
+                anims.push(Playing { kind: AnimKind::Pop, target: Target::Tile(x, y), start: now }),
+
+            Change::Conflict(x, y) =>
+                anims.push(Playing { kind: AnimKind::Shake, target: Target::Tile(x, y), start: now }),
+
+            Change::CrossedOut(..) => {},
+
+            Change::UnitCompleted(u) =>
+                for (i, &(x, y)) in u.tiles().iter().enumerate() {
+                    anims.push(Playing {
+                        kind: AnimKind::Sweep,
+                        target: Target::Tile(x, y),
+                        start: now + SWEEP_DELAY_MS * i as u32
+                    });
+                },
+
+            Change::Solved =>
+                anims.push(Playing { kind: AnimKind::Win, target: Target::Board, start: now })
+        }
+    }
+
+    anims
+}
+
@@ -0,0 +1,145 @@
+// atlas.rs
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use sdl2::rect::Rect;
+
+use gfx::Res;
+
+// The rectangle of each sprite in the graphics, see resource/sudoku.atlas.
+const DEFAULT_ATLAS: &'static str = include_str!("../resource/sudoku.atlas");
+
+pub struct Atlas {
+    rects: HashMap<Res, Rect>
+}
+
+// The sprite sizes the window is laid out with.
+#[derive(Clone,Copy)]
+pub struct Metrics {
+    pub tile_w: u32,
+    pub tile_h: u32,
+    pub label_w: u32,
+    pub undo_redo_w: u32,
+    pub button_w: u32,
+    pub button_h: u32,
+    pub number_w: u32
+}
+
+impl Atlas {
+    // The default atlas, then the file at path if given and it exists.
+    pub fn load(path: Option<&Path>) -> Atlas {
+        let mut atlas = Atlas { rects: HashMap::new() };
+
+        for (i, line) in DEFAULT_ATLAS.lines().enumerate() {
+            if let Err(e) = atlas.parse_line(line) {
+                panic!("default atlas:{}: {}", i + 1, e);
+            }
+        }
+
+        if let Some(path) = path {
+            if let Err(e) = atlas.load_file(path) {
+                eprintln!("{}: {}", path.display(), e);
+            }
+        }
+
+        atlas
+    }
+
+    fn load_file(&mut self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        match File::open(path) {
+            Ok(mut f) => f.read_to_string(&mut text)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e)
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            if let Err(e) = self.parse_line(line) {
+                eprintln!("{}:{}: {}", path.display(), i + 1, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (name, value) = match line.find('=') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(format!("expected <sprite> = <x> <y> <width> <height>: {}", line))
+        };
+
+        let res = res_from_name(name).ok_or(format!("unknown sprite: {}", name))?;
+        let nums: Option<Vec<u32>> = value.split_whitespace().map(|n| n.parse().ok()).collect();
+        match nums {
+            Some(ref n) if n.len() == 4 && n[2] > 0 && n[3] > 0 => {
+                self.rects.insert(res, Rect::new(n[0] as i32, n[1] as i32, n[2], n[3]));
+                Ok(())
+            },
+            _ => Err(format!("{}: bad rectangle: {}", name, value))
+        }
+    }
+
+    pub fn get(&self, res: Res) -> Option<Rect> {
+        self.rects.get(&res).cloned()
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        let size = |res: Res| self.get(res).map_or((1, 1), |r| (r.width(), r.height()));
+        let (tile_w, tile_h) = size(Res::TileInit(1));
+        let (button_w, button_h) = size(Res::ToolbarActivePencil);
+
+        Metrics {
+            tile_w: tile_w,
+            tile_h: tile_h,
+            label_w: size(Res::ToolbarSudoku).0,
+            undo_redo_w: size(Res::ToolbarUndo).0,
+            button_w: button_w,
+            button_h: button_h,
+            number_w: size(Res::ToolbarActiveNumber(1)).0
+        }
+    }
+}
+
+fn res_from_name(name: &str) -> Option<Res> {
+    let digit = |prefix: &str| if name.starts_with(prefix) {
+        name[prefix.len()..].parse::<u8>().ok().and_then(
+                |v| if 1 <= v && v <= 9 { Some(v) } else { None })
+    } else {
+        None
+    };
+
+    if let Some(v) = digit("number-active-") {
+        return Some(Res::ToolbarActiveNumber(v));
+    }
+    if let Some(v) = digit("number-inactive-") {
+        return Some(Res::ToolbarInactiveNumber(v));
+    }
+    if let Some(v) = digit("tile-init-") {
+        return Some(Res::TileInit(v));
+    }
+    if let Some(v) = digit("tile-guess-") {
+        return Some(Res::TileGuess(v));
+    }
+    if let Some(v) = digit("tile-conflict-") {
+        return Some(Res::TileConflict(v));
+    }
+
+    match name {
+        "sudoku" => Some(Res::ToolbarSudoku),
+        "undo" => Some(Res::ToolbarUndo),
+        "redo" => Some(Res::ToolbarRedo),
+        "pencil-active" => Some(Res::ToolbarActivePencil),
+        "pencil-inactive" => Some(Res::ToolbarInactivePencil),
+        "cross-out-active" => Some(Res::ToolbarActiveCrossOut),
+        "cross-out-inactive" => Some(Res::ToolbarInactiveCrossOut),
+        _ => None
+    }
+}
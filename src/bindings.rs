@@ -0,0 +1,442 @@
+// bindings.rs
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::PathBuf;
+use sdl2::keyboard::{Keycode,Mod};
+use sdl2::keyboard::{LCTRLMOD,RCTRLMOD,LSHIFTMOD,RSHIFTMOD,LALTMOD,RALTMOD};
+use sdl2::mouse::MouseButton;
+
+use paths;
+
+// The bindings file holds one binding per line, a key or mouse button with
+// any modifiers, then '=' and a command, e.g.
+//
+//   # undo and redo next to each other on a Dvorak layout
+//   ; = undo
+//   q = redo
+//   ctrl+shift+PageDown = next-puzzle
+//   mouse-middle = autofill
+//   z = none
+//
+// Keys are SDL key names.  Mouse buttons are mouse-middle, mouse-x1 and
+// mouse-x2; the left and right buttons always act on what is under the
+// pointer.  Each line replaces any default binding of the same input, and
+// 'none' removes it.
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Command {
+    New,
+    NextPuzzle,
+    PrevPuzzle,
+    GotoPuzzle,
+    Undo,
+    Redo,
+
+    // brushes
+    Pencil,
+    CrossOut,
+    Colour,
+    ClearColours,
+    Link,
+    ClearLinks,
+
+    AutoFill,
+    Check,
+    CheckMode,
+    Rewind,
+    Pause,
+    Stats,
+    HighlightValue,
+    HighlightPeers,
+    Export,
+    Save,
+    Load,
+    Copy,
+    CopyGrid,
+    Paste,
+    Fullscreen,
+
+    // cycles through the colour themes
+    Theme,
+
+    // turns the sound effects off or on
+    Mute,
+
+    Help,
+
+    // Value(v), selects v and assigns it at the cursor
+    Value(u8),
+
+    // Toggle(v), crosses out v at the cursor or in the selection, or
+    // restores it
+    Toggle(u8),
+
+    PrevValue,
+    NextValue,
+
+    // Move(dx,dy,select), moves the cursor, selecting the tiles passed over
+    Move(i8,i8,bool),
+
+    // applies the brush at the cursor
+    Apply,
+
+    // unassigns at the cursor or in the selection
+    Clear,
+
+    // clears a link being drawn, then the selection, then the cursor
+    Cancel
+}
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Input {
+    Key(Keycode),
+    Mouse(MouseButton)
+}
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool
+}
+
+#[derive(Clone,Copy)]
+pub struct Binding {
+    pub modifiers: Modifiers,
+    pub input: Input,
+    pub command: Command
+}
+
+pub struct Bindings {
+    bindings: Vec<Binding>
+}
+
+const DEFAULT_BINDINGS: &'static str = "\
+n = new
+PageDown = next-puzzle
+PageUp = prev-puzzle
+g = goto-puzzle
+z = undo
+x = redo
+mouse-x1 = undo
+mouse-x2 = redo
+c = pencil
+v = cross-out
+b = colour
+shift+b = clear-colours
+y = link
+shift+y = clear-links
+a = autofill
+w = check
+m = check-mode
+r = rewind
+p = pause
+i = stats
+d = highlight-value
+t = highlight-peers
+e = export
+ctrl+s = save
+ctrl+l = load
+ctrl+c = copy
+ctrl+shift+c = copy-grid
+ctrl+v = paste
+f = fullscreen
+F11 = fullscreen
+o = theme
+s = mute
+F1 = help
+shift+/ = help
+Left = left
+h = left
+Right = right
+l = right
+Up = up
+k = up
+Down = down
+j = down
+shift+Left = select-left
+shift+h = select-left
+shift+Right = select-right
+shift+l = select-right
+shift+Up = select-up
+shift+k = select-up
+shift+Down = select-down
+shift+j = select-down
+Return = apply
+Keypad Enter = apply
+Space = apply
+Delete = clear
+Backspace = clear
+Escape = cancel
+";
+
+impl Bindings {
+    // The defaults, then the bindings file if there is one.
+    pub fn load() -> Bindings {
+        let mut b = Bindings { bindings: Vec::new() };
+
+        for (i, line) in DEFAULT_BINDINGS.lines().enumerate() {
+            if let Err(e) = b.parse_line(line) {
+                panic!("default bindings:{}: {}", i + 1, e);
+            }
+        }
+
+        // 1-9 and keypad 1-9 select a value, shift+1-9 toggle it
+        for d in 1..9+1 {
+            b.bind(Modifiers::none(), Input::Key(digit_keycode(d, false)), Command::Value(d));
+            b.bind(Modifiers::none(), Input::Key(digit_keycode(d, true)), Command::Value(d));
+            b.bind(Modifiers { shift: true, .. Modifiers::none() },
+                    Input::Key(digit_keycode(d, false)), Command::Toggle(d));
+        }
+
+        if let Some(path) = bindings_path() {
+            if let Err(e) = b.load_file(&path) {
+                eprintln!("{}: {}", path.display(), e);
+            }
+        }
+
+        b
+    }
+
+    fn load_file(&mut self, path: &PathBuf) -> io::Result<()> {
+        let f = match File::open(path) {
+            Ok(f) => BufReader::new(f),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e)
+        };
+
+        // bad lines are skipped, so a typo does not lose every binding
+        for (i, line) in f.lines().enumerate() {
+            if let Err(e) = self.parse_line(&line?) {
+                eprintln!("{}:{}: {}", path.display(), i + 1, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (input, command) = match line.rfind('=') {
+            Some(i) if i > 0 => (line[..i].trim(), line[i + 1..].trim()),
+            _ => return Err(format!("expected <input> = <command>: {}", line))
+        };
+
+        let (modifiers, input) = parse_input(input)?;
+
+        self.bindings.retain(|b| b.modifiers != modifiers || b.input != input);
+        if command != "none" {
+            let c = Command::from_name(command)
+                .ok_or(format!("unknown command: {}", command))?;
+            self.bind(modifiers, input, c);
+        }
+
+        Ok(())
+    }
+
+    fn bind(&mut self, modifiers: Modifiers, input: Input, command: Command) {
+        self.bindings.push(Binding { modifiers: modifiers, input: input, command: command });
+    }
+
+    pub fn lookup(&self, input: Input, keymod: Mod) -> Option<Command> {
+        let modifiers = Modifiers::from_mod(keymod);
+        self.bindings.iter()
+            .find(|b| b.input == input && b.modifiers == modifiers)
+            .map(|b| b.command)
+    }
+
+    // Every command with a binding, in the order first bound, with the
+    // names of its inputs.
+    pub fn describe(&self) -> Vec<(String, Vec<String>)> {
+        let mut commands: Vec<(String, Vec<String>)> = Vec::new();
+
+        for b in self.bindings.iter() {
+            let name = b.command.name();
+            let input = b.describe_input();
+            match commands.iter().position(|&(ref n, _)| *n == name) {
+                Some(i) => commands[i].1.push(input),
+                None => commands.push((name, vec![input]))
+            }
+        }
+
+        commands
+    }
+}
+
+impl Binding {
+    fn describe_input(&self) -> String {
+        let mut s = String::new();
+        if self.modifiers.ctrl {
+            s.push_str("ctrl+");
+        }
+        if self.modifiers.alt {
+            s.push_str("alt+");
+        }
+        if self.modifiers.shift {
+            s.push_str("shift+");
+        }
+
+        match self.input {
+            Input::Key(k) => s.push_str(&k.name()),
+            Input::Mouse(MouseButton::Middle) => s.push_str("mouse-middle"),
+            Input::Mouse(MouseButton::X1) => s.push_str("mouse-x1"),
+            Input::Mouse(MouseButton::X2) => s.push_str("mouse-x2"),
+            Input::Mouse(_) => s.push_str("mouse")
+        }
+
+        s
+    }
+}
+
+impl Modifiers {
+    fn none() -> Modifiers {
+        Modifiers { ctrl: false, shift: false, alt: false }
+    }
+
+    pub fn from_mod(keymod: Mod) -> Modifiers {
+        Modifiers {
+            ctrl: keymod.intersects(LCTRLMOD | RCTRLMOD),
+            shift: keymod.intersects(LSHIFTMOD | RSHIFTMOD),
+            alt: keymod.intersects(LALTMOD | RALTMOD)
+        }
+    }
+}
+
+impl Command {
+    pub fn name(&self) -> String {
+        let name = match *self {
+            Command::New => "new",
+            Command::NextPuzzle => "next-puzzle",
+            Command::PrevPuzzle => "prev-puzzle",
+            Command::GotoPuzzle => "goto-puzzle",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::Pencil => "pencil",
+            Command::CrossOut => "cross-out",
+            Command::Colour => "colour",
+            Command::ClearColours => "clear-colours",
+            Command::Link => "link",
+            Command::ClearLinks => "clear-links",
+            Command::AutoFill => "autofill",
+            Command::Check => "check",
+            Command::CheckMode => "check-mode",
+            Command::Rewind => "rewind",
+            Command::Pause => "pause",
+            Command::Stats => "stats",
+            Command::HighlightValue => "highlight-value",
+            Command::HighlightPeers => "highlight-peers",
+            Command::Export => "export",
+            Command::Save => "save",
+            Command::Load => "load",
+            Command::Copy => "copy",
+            Command::CopyGrid => "copy-grid",
+            Command::Paste => "paste",
+            Command::Fullscreen => "fullscreen",
+            Command::Theme => "theme",
+            Command::Mute => "mute",
+            Command::Help => "help",
+            Command::Value(v) => return format!("value-{}", v),
+            Command::Toggle(v) => return format!("toggle-{}", v),
+            Command::PrevValue => "prev-value",
+            Command::NextValue => "next-value",
+            Command::Move(-1, 0, false) => "left",
+            Command::Move(1, 0, false) => "right",
+            Command::Move(0, -1, false) => "up",
+            Command::Move(0, 1, false) => "down",
+            Command::Move(-1, 0, true) => "select-left",
+            Command::Move(1, 0, true) => "select-right",
+            Command::Move(0, -1, true) => "select-up",
+            Command::Move(0, 1, true) => "select-down",
+            Command::Move(..) => "move",
+            Command::Apply => "apply",
+            Command::Clear => "clear",
+            Command::Cancel => "cancel"
+        };
+
+        String::from(name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Command> {
+        let digit = |prefix: &str| if name.starts_with(prefix) {
+            name[prefix.len()..].parse::<u8>().ok().and_then(
+                    |v| if 1 <= v && v <= 9 { Some(v) } else { None })
+        } else {
+            None
+        };
+
+        if let Some(v) = digit("value-") {
+            return Some(Command::Value(v));
+        }
+        if let Some(v) = digit("toggle-") {
+            return Some(Command::Toggle(v));
+        }
+
+        let commands = [
+            Command::New, Command::NextPuzzle, Command::PrevPuzzle, Command::GotoPuzzle,
+            Command::Undo, Command::Redo, Command::Pencil, Command::CrossOut,
+            Command::Colour, Command::ClearColours, Command::Link, Command::ClearLinks,
+            Command::AutoFill, Command::Check, Command::CheckMode, Command::Rewind,
+            Command::Pause, Command::Stats, Command::HighlightValue, Command::HighlightPeers,
+            Command::Export, Command::Save, Command::Load, Command::Copy, Command::CopyGrid,
+            Command::Paste, Command::Fullscreen, Command::Theme, Command::Mute,
+            Command::Help,
+            Command::PrevValue, Command::NextValue,
+            Command::Move(-1, 0, false), Command::Move(1, 0, false),
+            Command::Move(0, -1, false), Command::Move(0, 1, false),
+            Command::Move(-1, 0, true), Command::Move(1, 0, true),
+            Command::Move(0, -1, true), Command::Move(0, 1, true),
+            Command::Apply, Command::Clear, Command::Cancel];
+
+        commands.iter().find(|c| c.name() == name).cloned()
+    }
+}
+
+pub fn bindings_path() -> Option<PathBuf> {
+    paths::config_dir().map(|d| d.join("bindings.txt"))
+}
+
+// "ctrl+shift+PageDown" or "mouse-x1"
+fn parse_input(s: &str) -> Result<(Modifiers, Input), String> {
+    let mut modifiers = Modifiers::none();
+    let mut rest = s;
+
+    // anything else before a '+' is part of the key, as in "Keypad +"
+    while let Some(i) = rest.find('+') {
+        match rest[..i].trim().to_lowercase().as_str() {
+            "ctrl" => modifiers.ctrl = true,
+            "shift" => modifiers.shift = true,
+            "alt" => modifiers.alt = true,
+            _ => break
+        }
+        rest = &rest[i + 1..];
+    }
+
+    let input = match rest.trim().to_lowercase().as_str() {
+        "mouse-middle" => Input::Mouse(MouseButton::Middle),
+        "mouse-x1" => Input::Mouse(MouseButton::X1),
+        "mouse-x2" => Input::Mouse(MouseButton::X2),
+        name => Input::Key(Keycode::from_name(name)
+                .ok_or(format!("unknown key: {}", rest.trim()))?)
+    };
+
+    Ok((modifiers, input))
+}
+
+fn digit_keycode(d: u8, keypad: bool) -> Keycode {
+    let keys = if keypad {
+        [Keycode::Kp1, Keycode::Kp2, Keycode::Kp3, Keycode::Kp4, Keycode::Kp5,
+         Keycode::Kp6, Keycode::Kp7, Keycode::Kp8, Keycode::Kp9]
+    } else {
+        [Keycode::Num1, Keycode::Num2, Keycode::Num3, Keycode::Num4, Keycode::Num5,
+         Keycode::Num6, Keycode::Num7, Keycode::Num8, Keycode::Num9]
+    };
+
+    keys[(d - 1) as usize]
+}
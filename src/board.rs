@@ -1,9 +1,76 @@
 // board.rs
 
+use solver;
 use tile::Tile;
 
+pub const NUM_COLOURS: u8 = 6;
+
+#[derive(Clone)]
 pub struct Board {
-    tiles: Vec<Tile>
+    tiles: Vec<Tile>,
+    annotations: Annotations
+}
+
+// Colours and links drawn by the player, kept with the board so they are
+// undone like any other move.  0 is no colour.
+#[derive(Clone)]
+struct Annotations {
+    // 81 tiles, row-major
+    tile_colours: Vec<u8>,
+
+    // 9 candidates for each tile
+    candidate_colours: Vec<u8>,
+
+    links: Vec<Link>
+}
+
+// A strong or weak link between candidates (x, y, v) in different tiles,
+// for working through chains.  from is always the smaller end, so a link
+// drawn either way round is the same link.
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub struct Link {
+    pub from: (u8,u8,u8),
+    pub to: (u8,u8,u8),
+    pub strong: bool
+}
+
+// A row, column or block, by its index from the top left.
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Unit {
+    Row(u8),
+    Column(u8),
+    Block(u8)
+}
+
+// Something that happened between one board and the next.
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Change {
+    // a number was assigned at (x, y) without a conflict
+    Assigned(u8,u8),
+
+    // the number at (x, y) now conflicts, having just been assigned or
+    // been conflicted with
+    Conflict(u8,u8),
+
+    // a candidate was crossed out at (x, y)
+    CrossedOut(u8,u8),
+
+    // the unit was filled in without conflicts
+    UnitCompleted(Unit),
+
+    Solved
+}
+
+impl Unit {
+    // The tiles of the unit, left to right and top to bottom.
+    pub fn tiles(&self) -> Vec<(u8,u8)> {
+        match *self {
+            Unit::Row(y) => (0..9).map(|x| (x, y)).collect(),
+            Unit::Column(x) => (0..9).map(|y| (x, y)).collect(),
+            Unit::Block(b) =>
+                (0..9).map(|i| (3 * (b % 3) + i % 3, 3 * (b / 3) + i / 3)).collect()
+        }
+    }
 }
 
 impl Board {
@@ -18,7 +85,12 @@ impl Board {
         }
 
         Board {
-            tiles: ts
+            tiles: ts,
+            annotations: Annotations {
+                tile_colours: vec![0; 81],
+                candidate_colours: vec![0; 81 * 9],
+                links: Vec::new()
+            }
         }
     }
 
@@ -35,8 +107,18 @@ impl Board {
                 ts.push(new_t);
             }
 
+            // the candidates of the tile are no longer shown, so neither
+            // are their colours and links
+            let mut a = self.annotations.clone();
+            let i = 9 * (9 * y as usize + x as usize);
+            for c in a.candidate_colours[i..i + 9].iter_mut() {
+                *c = 0;
+            }
+            a.links.retain(|l| (l.from.0, l.from.1) != (x, y) && (l.to.0, l.to.1) != (x, y));
+
             Some(Board {
-                tiles: ts
+                tiles: ts,
+                annotations: a
             })
         } else {
             None
@@ -56,7 +138,8 @@ impl Board {
             }
 
             let mut b = Board {
-                tiles: ts
+                tiles: ts,
+                annotations: self.annotations.clone()
             };
 
             for t in self.tiles.iter() {
@@ -87,17 +170,177 @@ impl Board {
             }
 
             Some(Board {
-                tiles: ts
+                tiles: ts,
+                annotations: self.annotations.clone()
+            })
+        } else {
+            None
+        }
+    }
+
+    fn is_valid_restore_value(&self, x: u8, y: u8, v: u8) -> bool {
+        self.tiles.iter().all(|t| t.is_valid_restore_value(x, y, v))
+    }
+
+    pub fn restore_value(&self, x: u8, y: u8, v: u8) -> Option<Board> {
+        if self.is_valid_restore_value(x, y, v) {
+            let mut ts: Vec<Tile> = Vec::new();
+
+            for old_t in self.tiles.iter() {
+                let new_t = old_t.restore_value(x, y, v);
+                ts.push(new_t);
+            }
+
+            Some(Board {
+                tiles: ts,
+                annotations: self.annotations.clone()
             })
         } else {
             None
         }
     }
 
+    pub fn tile_colour(&self, x: u8, y: u8) -> u8 {
+        if x < 9 && y < 9 {
+            self.annotations.tile_colours[9 * y as usize + x as usize]
+        } else {
+            0
+        }
+    }
+
+    pub fn candidate_colour(&self, x: u8, y: u8, v: u8) -> u8 {
+        if x < 9 && y < 9 && 1 <= v && v <= 9 {
+            self.annotations.candidate_colours[9 * (9 * y as usize + x as usize) + (v - 1) as usize]
+        } else {
+            0
+        }
+    }
+
+    pub fn colour_tile(&self, x: u8, y: u8, c: u8) -> Option<Board> {
+        if x < 9 && y < 9 && c <= NUM_COLOURS && self.tile_colour(x, y) != c {
+            let mut b = self.clone();
+            b.annotations.tile_colours[9 * y as usize + x as usize] = c;
+            Some(b)
+        } else {
+            None
+        }
+    }
+
+    // a candidate drawn in an unassigned tile
+    pub fn is_shown_candidate(&self, x: u8, y: u8, v: u8) -> bool {
+        self.get(x, y).map_or(false, |t| t.assignment.is_none()
+                && t.candidates.contains(&v) && !t.eliminated.contains(&v))
+    }
+
+    // Only candidates that are shown can be coloured.
+    pub fn colour_candidate(&self, x: u8, y: u8, v: u8, c: u8) -> Option<Board> {
+        if self.is_shown_candidate(x, y, v) && c <= NUM_COLOURS && self.candidate_colour(x, y, v) != c {
+            let mut b = self.clone();
+            b.annotations.candidate_colours[
+                9 * (9 * y as usize + x as usize) + (v - 1) as usize] = c;
+            Some(b)
+        } else {
+            None
+        }
+    }
+
+    pub fn clear_colours(&self) -> Option<Board> {
+        let a = &self.annotations;
+        if a.tile_colours.iter().chain(a.candidate_colours.iter()).all(|&c| c == 0) {
+            return None;
+        }
+
+        let mut b = self.clone();
+        b.annotations.tile_colours = vec![0; 81];
+        b.annotations.candidate_colours = vec![0; 81 * 9];
+        Some(b)
+    }
+
+    pub fn links(&self) -> &Vec<Link> {
+        &self.annotations.links
+    }
+
+    // Adds a link, changes its kind, or removes it if it is already there
+    // with the same kind.  Only candidates that are shown can be linked.
+    pub fn toggle_link(&self, a: (u8,u8,u8), b: (u8,u8,u8), strong: bool) -> Option<Board> {
+        if (a.0, a.1) == (b.0, b.1)
+                || !self.is_shown_candidate(a.0, a.1, a.2)
+                || !self.is_shown_candidate(b.0, b.1, b.2) {
+            return None;
+        }
+
+        let (from, to) = if a < b { (a, b) } else { (b, a) };
+        let mut bd = self.clone();
+
+        {
+            let links = &mut bd.annotations.links;
+            match links.iter().position(|l| l.from == from && l.to == to) {
+                Some(i) if links[i].strong == strong => { links.remove(i); },
+                Some(i) => links[i].strong = strong,
+                None => links.push(Link { from: from, to: to, strong: strong })
+            }
+        }
+
+        Some(bd)
+    }
+
+    pub fn clear_links(&self) -> Option<Board> {
+        if self.annotations.links.is_empty() {
+            return None;
+        }
+
+        let mut b = self.clone();
+        b.annotations.links.clear();
+        Some(b)
+    }
+
     pub fn get(&self, x: u8, y: u8) -> Option<&Tile> {
         self.tiles.iter().find(|t| t.x == x && t.y == y)
     }
 
+    // every tile assigned, without conflicts, including between givens
+    pub fn is_solved(&self) -> bool {
+        self.tiles.iter().all(|t| t.assignment.is_some())
+            && solver::is_consistent(&solver::from_board(self, false))
+    }
+
+    // What changed from old to this board.  A board for another puzzle
+    // has no changes, rather than every number assigned.
+    pub fn changes_from(&self, old: &Board) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let is_conflict = |t: &Tile| t.assignment.is_some() && !t.is_init() && !t.is_guess();
+        let is_done = |b: &Board, u: &Unit| u.tiles().iter().all(
+                |&(x, y)| b.get(x, y).map_or(false, |t| t.is_init() || t.is_guess()));
+
+        if self.tiles.iter().zip(old.tiles.iter()).any(|(t1, t2)| t1.init != t2.init) {
+            return changes;
+        }
+
+        for (t, t_old) in self.tiles.iter().zip(old.tiles.iter()) {
+            if is_conflict(t) && (!is_conflict(t_old) || t.assignment != t_old.assignment) {
+                changes.push(Change::Conflict(t.x, t.y));
+            } else if t.assignment.is_some() && t_old.assignment.is_none() {
+                changes.push(Change::Assigned(t.x, t.y));
+            } else if t.assignment.is_none() && t.eliminated.len() > t_old.eliminated.len() {
+                changes.push(Change::CrossedOut(t.x, t.y));
+            }
+        }
+
+        for i in 0..9 {
+            for &u in [Unit::Row(i), Unit::Column(i), Unit::Block(i)].iter() {
+                if is_done(self, &u) && !is_done(old, &u) {
+                    changes.push(Change::UnitCompleted(u));
+                }
+            }
+        }
+
+        if self.is_solved() && !old.is_solved() {
+            changes.push(Change::Solved);
+        }
+
+        changes
+    }
+
     pub fn is_unique(&self, tile: &Tile, value: u8) -> bool {
         tile.is_remaining_candidate(value)
             || self.is_unique_in_row(tile.x, tile.y, value)
@@ -178,3 +421,41 @@ impl Board {
     }
     */
 }
+
+#[cfg(test)]
+mod tests {
+    use puzzle::parse_puzzles;
+    use solver::tests::{SOLUTION,UNIQUE};
+
+    #[test]
+    fn solved_when_filled_without_conflicts() {
+        let b = parse_puzzles(&format!(".{}", &SOLUTION[1..])).pop().unwrap();
+        assert!(!b.is_solved());
+        assert!(!b.assign_value(0, 0, 1, false).unwrap().is_solved());
+        assert!(b.assign_value(0, 0, 4, false).unwrap().is_solved());
+    }
+
+    #[test]
+    fn assigning_clears_candidate_annotations() {
+        let b = parse_puzzles(UNIQUE).pop().unwrap()
+            .colour_tile(1, 0, 2).unwrap()
+            .colour_candidate(1, 0, 8, 1).unwrap()
+            .colour_candidate(1, 1, 8, 1).unwrap()
+            .toggle_link((1, 0, 8), (1, 1, 8), true).unwrap()
+            .toggle_link((1, 1, 4), (4, 1, 4), false).unwrap();
+
+        let b = b.assign_value(1, 0, 8, false).unwrap();
+        assert_eq!(b.candidate_colour(1, 0, 8), 0);
+        assert_eq!(b.candidate_colour(1, 1, 8), 1);
+        assert_eq!(b.tile_colour(1, 0), 2);
+        assert_eq!(b.links().len(), 1);
+        assert!(b.links()[0].from == (1, 1, 4));
+    }
+
+    #[test]
+    fn conflicting_givens_are_not_solved() {
+        // the top row with 3 twice and no 4
+        let b = parse_puzzles(&format!("3{}", &SOLUTION[1..])).pop().unwrap();
+        assert!(!b.is_solved());
+    }
+}
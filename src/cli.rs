@@ -0,0 +1,457 @@
+// cli.rs
+
+use generator::generate;
+use puzzle::PuzzleCollection;
+use rating::{Difficulty,rate};
+use solver;
+use solver::{Grid,Rng};
+use stats;
+use stats::PuzzleStats;
+use stopwatch::format_ms;
+
+pub const USAGE: &'static str = "\
+usage: play_sudoku [play] [<file>] [--record <log>]
+       play_sudoku tui [<file>] [--record <log>]
+       play_sudoku replay <log> [--fast]
+       play_sudoku solve <file> [--json]
+       play_sudoku count <file> [--limit N] [--json]
+       play_sudoku rate <file> [--json]
+       play_sudoku generate [--difficulty easy|medium|hard|expert] [--count N] [--json]
+       play_sudoku validate <file> [--json]
+       play_sudoku stats [<file>] [--json]
+";
+
+const DEFAULT_COUNT_LIMIT: usize = 1000;
+
+pub enum Command {
+    Help,
+
+    // Play(filename, log)
+    Play(Option<String>, Option<String>),
+
+    // Tui(filename, log)
+    Tui(Option<String>, Option<String>),
+
+    // Replay(log, fast)
+    Replay(String, bool),
+
+    // Solve(filename)
+    Solve(String),
+
+    // Count(filename, limit)
+    Count(String, usize),
+
+    // Rate(filename)
+    Rate(String),
+
+    // Generate(difficulty, count)
+    Generate(Difficulty, usize),
+
+    // Validate(filename)
+    Validate(String),
+
+    // Stats(filename), all puzzles played if None
+    Stats(Option<String>),
+}
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json
+}
+
+pub fn parse_args(args: &[String]) -> Result<(Command, OutputFormat), String> {
+    let mut format = OutputFormat::Text;
+    let mut difficulty = Difficulty::Medium;
+    let mut count: usize = 1;
+    let mut limit = DEFAULT_COUNT_LIMIT;
+    let mut record: Option<String> = None;
+    let mut fast = false;
+    let mut positional: Vec<&String> = Vec::new();
+
+    // options given that apply to only some commands
+    let mut given: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < args.len() {
+        let arg = &args[i];
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok((Command::Help, format)),
+            "--json" => format = OutputFormat::Json,
+            "--fast" => {
+                fast = true;
+                given.push(arg);
+            },
+
+            "--record" => {
+                given.push(arg);
+                record = Some(String::from(option_value(args, i)?));
+                i = i + 1;
+            },
+
+            "--difficulty" => {
+                given.push(arg);
+                let value = option_value(args, i)?;
+                difficulty = Difficulty::from_name(value)
+                    .ok_or(format!("unknown difficulty: {}", value))?;
+                i = i + 1;
+            },
+
+            "--count" | "--limit" => {
+                given.push(arg);
+                let value = option_value(args, i)?;
+                let n = value.parse::<usize>()
+                    .map_err(|_| format!("{}: not a number: {}", arg, value))?;
+                if arg == "--count" {
+                    count = n;
+                } else if n == 0 {
+                    // counting every solution of a sparse grid never ends
+                    return Err(format!("{}: must be at least 1", arg));
+                } else {
+                    limit = n;
+                }
+                i = i + 1;
+            },
+
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+
+            _ => positional.push(arg)
+        }
+
+        i = i + 1;
+    }
+
+    let filename = |n: usize| -> Result<String, String> {
+        let len = positional.len();
+        if len < n {
+            Err(format!("{}: missing file", positional[0]))
+        } else if len > n {
+            Err(format!("{}: too many arguments", positional[0]))
+        } else {
+            Ok(positional[n - 1].clone())
+        }
+    };
+
+    let command = match positional.first().map(|s| s.as_str()) {
+        None => Command::Play(None, record),
+        Some("play") =>
+            match positional.len() {
+                1 => Command::Play(None, record),
+                2 => Command::Play(Some(positional[1].clone()), record),
+                _ => return Err(String::from("play: too many arguments"))
+            },
+        Some("tui") =>
+            match positional.len() {
+                1 => Command::Tui(None, record),
+                2 => Command::Tui(Some(positional[1].clone()), record),
+                _ => return Err(String::from("tui: too many arguments"))
+            },
+        Some("replay") => Command::Replay(filename(2)?, fast),
+        Some("solve") => Command::Solve(filename(2)?),
+        Some("count") => Command::Count(filename(2)?, limit),
+        Some("rate") => Command::Rate(filename(2)?),
+        Some("validate") => Command::Validate(filename(2)?),
+        Some("stats") =>
+            match positional.len() {
+                1 => Command::Stats(None),
+                2 => Command::Stats(Some(positional[1].clone())),
+                _ => return Err(String::from("stats: too many arguments"))
+            },
+        Some("generate") =>
+            if positional.len() == 1 {
+                Command::Generate(difficulty, count)
+            } else {
+                return Err(String::from("generate: too many arguments"));
+            },
+
+        // play_sudoku <file>
+        Some(_) =>
+            if positional.len() == 1 {
+                Command::Play(Some(positional[0].clone()), record)
+            } else {
+                return Err(String::from("too many arguments"));
+            }
+    };
+
+    let allowed: &[&str] = match command {
+        Command::Play(..) | Command::Tui(..) => &["--record"],
+        Command::Replay(..) => &["--fast"],
+        Command::Count(..) => &["--limit"],
+        Command::Generate(..) => &["--difficulty", "--count"],
+        _ => &[]
+    };
+
+    if let Some(o) = given.iter().find(|o| !allowed.contains(o)) {
+        let name = positional.first().map_or("play", |s| s.as_str());
+        return Err(format!("{}: {} does not apply", name, o));
+    }
+
+    Ok((command, format))
+}
+
+fn option_value(args: &[String], i: usize) -> Result<&str, String> {
+    match args.get(i + 1) {
+        Some(v) => Ok(v.as_str()),
+        None => Err(format!("{}: missing value", args[i]))
+    }
+}
+
+// Runs a headless command.  Returns the process exit code.
+pub fn run(command: Command, format: OutputFormat) -> i32 {
+    match command {
+        Command::Help => {
+            print!("{}", USAGE);
+            0
+        },
+
+        Command::Play(..) | Command::Tui(..) | Command::Replay(..) =>
+            panic!("not a headless command"),
+        Command::Solve(filename) => with_puzzles(&filename, format, solve),
+        Command::Count(filename, limit) =>
+            with_puzzles(&filename, format, |grid| count(grid, limit)),
+        Command::Rate(filename) => with_puzzles(&filename, format, rate_puzzle),
+        Command::Validate(filename) => with_puzzles(&filename, format, validate),
+        Command::Generate(difficulty, n) => generate_puzzles(difficulty, n, format),
+        Command::Stats(filename) => print_stats(filename, format)
+    }
+}
+
+// A result is a list of (key, value) pairs and whether it succeeded.
+// Values are written as they are, so string values must be quoted.
+type Output = (Vec<(&'static str, String)>, bool);
+
+fn with_puzzles<F>(filename: &String, format: OutputFormat, f: F) -> i32
+        where F: Fn(&Grid) -> Output {
+    let c = match PuzzleCollection::load(filename) {
+        Some(c) => c,
+        None => return 1
+    };
+
+    let mut results = Vec::new();
+    let mut ok = true;
+
+    for (n, b) in c.puzzles().iter().enumerate() {
+        let (mut fields, success) = f(&solver::from_board(b, true));
+        fields.insert(0, ("index", format!("{}", n)));
+        results.push(fields);
+        ok = ok && success;
+    }
+
+    print_results(&results, format);
+    if ok { 0 } else { 1 }
+}
+
+// Err(reason) unless the puzzle has exactly one solution.
+fn check(grid: &Grid) -> Result<Grid, &'static str> {
+    if !solver::is_consistent(grid) {
+        return Err("conflicting givens");
+    }
+
+    match solver::count_solutions(grid, 2) {
+        0 => Err("no solution"),
+        1 => Ok(solver::solve(grid).unwrap()),
+        _ => Err("multiple solutions")
+    }
+}
+
+fn solve(grid: &Grid) -> Output {
+    match check(grid) {
+        Ok(solution) =>
+            (vec![("status", quote("solved")),
+                  ("solution", quote(&solver::to_line(&solution)))],
+             true),
+        Err(e) => (vec![("status", quote(e))], false)
+    }
+}
+
+fn count(grid: &Grid, limit: usize) -> Output {
+    let n = if solver::is_consistent(grid) {
+        solver::count_solutions(grid, limit)
+    } else {
+        0
+    };
+
+    (vec![("solutions", format!("{}", n)),
+          ("limit_reached", format!("{}", n >= limit))],
+     true)
+}
+
+fn rate_puzzle(grid: &Grid) -> Output {
+    match check(grid) {
+        Ok(_) =>
+            (vec![("status", quote("valid")),
+                  ("difficulty", quote(rate(grid).name()))],
+             true),
+        Err(e) => (vec![("status", quote(e))], false)
+    }
+}
+
+fn validate(grid: &Grid) -> Output {
+    match check(grid) {
+        Ok(_) => (vec![("status", quote("valid"))], true),
+        Err(e) => (vec![("status", quote(e))], false)
+    }
+}
+
+fn generate_puzzles(difficulty: Difficulty, n: usize, format: OutputFormat) -> i32 {
+    let mut rng = Rng::from_time();
+    let mut results = Vec::new();
+
+    for _ in 0..n {
+        match generate(difficulty, &mut rng) {
+            Some(puzzle) =>
+                results.push(vec![
+                        ("puzzle", quote(&solver::to_line(&puzzle))),
+                        ("difficulty", quote(difficulty.name()))]),
+            None => {
+                eprintln!("generate: no {} puzzle found", difficulty.name());
+                print_results(&results, format);
+                return 1;
+            }
+        }
+    }
+
+    print_results(&results, format);
+    0
+}
+
+fn print_stats(filename: Option<String>, format: OutputFormat) -> i32 {
+    let all = match stats::load() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("stats: {}", e);
+            return 1;
+        }
+    };
+
+    let mut results = Vec::new();
+
+    match filename {
+        None =>
+            for (k, s) in all.iter() {
+                let mut fields = stats_fields(s);
+                fields.insert(0, ("key", quote(k)));
+                results.push(fields);
+            },
+
+        // the puzzles of a file, whether played or not
+        Some(filename) => {
+            let c = match PuzzleCollection::load(&filename) {
+                Some(c) => c,
+                None => return 1
+            };
+
+            for (n, b) in c.puzzles().iter().enumerate() {
+                let k = stats::key(b).unwrap_or(String::new());
+                let mut fields = match all.get(&k) {
+                    Some(s) => stats_fields(s),
+                    None => stats_fields(&PuzzleStats::new())
+                };
+                fields.insert(0, ("key", quote(&k)));
+                fields.insert(0, ("index", format!("{}", n)));
+                results.push(fields);
+            }
+        }
+    }
+
+    print_results(&results, format);
+    0
+}
+
+fn stats_fields(s: &PuzzleStats) -> Vec<(&'static str, String)> {
+    let last_played = if s.last_played.is_empty() { "-" } else { &s.last_played };
+
+    vec![("attempts", format!("{}", s.attempts)),
+         ("completions", format!("{}", s.completions)),
+         ("best", quote(&s.best.map_or(String::from("-"), format_ms))),
+         ("hints", format!("{}", s.hints)),
+         ("last_played", quote(last_played))]
+}
+
+// Text output is one line per result, with the values separated by spaces.
+fn print_results(results: &Vec<Vec<(&'static str, String)>>, format: OutputFormat) {
+    match format {
+        OutputFormat::Text =>
+            for fields in results.iter() {
+                let values: Vec<&str> = fields.iter()
+                    .map(|&(_, ref v)| v.trim_matches('"'))
+                    .collect();
+                println!("{}", values.join(" "));
+            },
+
+        OutputFormat::Json => {
+            println!("[");
+            for (i, fields) in results.iter().enumerate() {
+                let pairs: Vec<String> = fields.iter()
+                    .map(|&(k, ref v)| format!("\"{}\": {}", k, v))
+                    .collect();
+                let sep = if i + 1 < results.len() { "," } else { "" };
+                println!("  {{{}}}{}", pairs.join(", "), sep);
+            }
+            println!("]");
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::tests::{UNIQUE,grid};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn parse(line: &str) -> Result<Command, String> {
+        parse_args(&args(line)).map(|(c, _)| c)
+    }
+
+
+    #[test]
+    fn options_of_each_command() {
+        match parse("count p.txt --limit 5 --json") {
+            Ok(Command::Count(ref f, 5)) => assert_eq!(f, "p.txt"),
+            _ => panic!("count")
+        }
+        match parse("generate --difficulty hard --count 3") {
+            Ok(Command::Generate(Difficulty::Hard, 3)) => {},
+            _ => panic!("generate")
+        }
+        match parse("replay game.log --fast") {
+            Ok(Command::Replay(ref f, true)) => assert_eq!(f, "game.log"),
+            _ => panic!("replay")
+        }
+        match parse("p.txt --record game.log") {
+            Ok(Command::Play(Some(_), Some(ref l))) => assert_eq!(l, "game.log"),
+            _ => panic!("play")
+        }
+    }
+
+    #[test]
+    fn options_of_other_commands_are_rejected() {
+        for line in ["solve p.txt --limit 5", "rate p.txt --count 2", "validate p.txt --fast",
+                "count p.txt --difficulty easy", "generate --record game.log",
+                "play --fast", "stats --limit 1", "replay game.log --record x.log"].iter() {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn zero_limit_is_rejected() {
+        assert!(parse("count p.txt --limit 0").is_err());
+    }
+
+    #[test]
+    fn check_statuses() {
+        assert!(check(&grid(UNIQUE)).is_ok());
+        assert_eq!(check(&[0; 81]).err(), Some("multiple solutions"));
+        assert_eq!(check(&grid(&format!("3{}", &UNIQUE[1..]))).err(), Some("conflicting givens"));
+        assert_eq!(check(&grid(&format!("12345678.{}9", ".".repeat(8)))).err(),
+                Some("no solution"));
+    }
+}
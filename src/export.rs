@@ -0,0 +1,218 @@
+// export.rs
+
+use std::cmp::max;
+
+use board::Board;
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum ExportFormat {
+    Grid,
+    Line,
+    PencilMarks
+}
+
+impl ExportFormat {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ExportFormat::Grid => "grid",
+            ExportFormat::Line => "line",
+            ExportFormat::PencilMarks => "pencil-marks"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name {
+            "grid" => Some(ExportFormat::Grid),
+            "line" => Some(ExportFormat::Line),
+            "pencil-marks" => Some(ExportFormat::PencilMarks),
+            _ => None
+        }
+    }
+}
+
+pub fn export(board: &Board, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Grid => to_grid(board),
+        ExportFormat::Line => to_line(board),
+        ExportFormat::PencilMarks => to_pencil_marks(board)
+    }
+}
+
+// 9x9 grid with box borders, e.g.
+//
+//   +-------+-------+-------+
+//   | . . . | . . . | . . . |
+//   | . . . | . . 3 | . 8 5 |
+//   ...
+//
+// The output can be loaded back as a puzzle.
+pub fn to_grid(board: &Board) -> String {
+    let border = "+-------+-------+-------+\n";
+    let mut s = String::new();
+
+    for y in 0..9 {
+        if y % 3 == 0 {
+            s.push_str(border);
+        }
+
+        for x in 0..9 {
+            if x % 3 == 0 {
+                s.push_str("| ");
+            }
+            s.push(cell_char(board, x, y));
+            s.push(' ');
+        }
+        s.push_str("|\n");
+    }
+
+    s.push_str(border);
+    s
+}
+
+// 81 characters on a single line, '.' for unassigned tiles.
+pub fn to_line(board: &Board) -> String {
+    let mut s = String::new();
+
+    for y in 0..9 {
+        for x in 0..9 {
+            s.push(cell_char(board, x, y));
+        }
+    }
+
+    s.push('\n');
+    s
+}
+
+// 9x9 grid of the remaining candidates of each tile, i.e. the candidates
+// that have not been crossed out.  Assigned tiles show their value.
+pub fn to_pencil_marks(board: &Board) -> String {
+    let mut cells: Vec<String> = Vec::new();
+    let mut widths = [1; 9];
+
+    for y in 0..9 {
+        for x in 0..9 {
+            let cell = pencil_marks(board, x, y);
+            widths[x as usize] = max(widths[x as usize], cell.len());
+            cells.push(cell);
+        }
+    }
+
+    let mut border = String::new();
+    for bx in 0..3 {
+        border.push('+');
+        let w: usize = widths[3 * bx..3 * bx + 3].iter().map(|&w| w + 1).sum();
+        for _ in 0..w + 1 {
+            border.push('-');
+        }
+    }
+    border.push_str("+\n");
+
+    let mut s = String::new();
+    for y in 0..9 {
+        if y % 3 == 0 {
+            s.push_str(&border);
+        }
+
+        for x in 0..9 {
+            if x % 3 == 0 {
+                s.push_str("| ");
+            }
+            s.push_str(&format!("{:1$} ", cells[9 * y + x], widths[x]));
+        }
+        s.push_str("|\n");
+    }
+
+    s.push_str(&border);
+    s
+}
+
+fn cell_char(board: &Board, x: u8, y: u8) -> char {
+    match board.get(x, y).and_then(|t| t.assignment) {
+        Some(v) => (b'0' + v) as char,
+        None => '.'
+    }
+}
+
+fn pencil_marks(board: &Board, x: u8, y: u8) -> String {
+    let mut s = String::new();
+
+    if let Some(t) = board.get(x, y) {
+        if let Some(v) = t.assignment {
+            s.push((b'0' + v) as char);
+        } else {
+            for &v in t.candidates.iter().filter(
+                    |&&v1| t.eliminated.iter().all(|&v2| v1 != v2)) {
+                s.push((b'0' + v) as char);
+            }
+        }
+    }
+
+    if s.is_empty() {
+        s.push('-');
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_grid,to_line,to_pencil_marks};
+    use board::Board;
+    use puzzle::parse_puzzles;
+    use solver::tests::UNIQUE;
+
+    const UNIQUE_GRID: &'static str = "\
++-------+-------+-------+
+| . . 3 | . 2 . | 6 . . |
+| 9 . . | 3 . 5 | . . 1 |
+| . . 1 | 8 . 6 | 4 . . |
++-------+-------+-------+
+| . . 8 | 1 . 2 | 9 . . |
+| 7 . . | . . . | . . 8 |
+| . . 6 | 7 . 8 | 2 . . |
++-------+-------+-------+
+| . . 2 | 6 . 9 | 5 . . |
+| 8 . . | 2 . 3 | . . 9 |
+| . . 5 | . 1 . | 3 . . |
++-------+-------+-------+
+";
+
+    fn board() -> Board {
+        parse_puzzles(UNIQUE).remove(0)
+    }
+
+    #[test]
+    fn grid() {
+        assert_eq!(to_grid(&board()), UNIQUE_GRID);
+    }
+
+    #[test]
+    fn line() {
+        assert_eq!(to_line(&board()), format!("{}\n", UNIQUE));
+    }
+
+    #[test]
+    fn exports_read_back() {
+        for text in [to_line(&board()), to_grid(&board())].iter() {
+            let puzzles = parse_puzzles(text);
+            assert_eq!(puzzles.len(), 1);
+            assert_eq!(to_line(&puzzles[0]), to_line(&board()));
+        }
+    }
+
+    #[test]
+    fn pencil_marks_leave_out_eliminated() {
+        let board = Board::new()
+            .assign_value(4, 0, 5, false).unwrap()
+            .cross_out_value(0, 0, 1).unwrap();
+        let text = to_pencil_marks(&board);
+        let rows: Vec<&str> = text.lines().collect();
+
+        assert_eq!(rows[1],
+                "| 2346789   12346789  12346789  | 12346789  5        12346789  \
+                 | 12346789  12346789  12346789  |");
+        assert_eq!(rows[5],
+                "| 123456789 123456789 123456789 | 123456789 12346789 123456789 \
+                 | 123456789 123456789 123456789 |");
+    }
+}
@@ -0,0 +1,68 @@
+// frontend.rs
+
+use action::SudokuAction;
+use board::Board;
+use export;
+use export::ExportFormat;
+use game::{Game,GameStatus};
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Brush {
+    Pencil,
+    CrossOut,
+
+    // paints the selected colour onto tiles or candidates
+    Colour,
+
+    // draws links between candidates
+    Link
+}
+
+pub trait Frontend {
+    fn read_input(&mut self) -> SudokuAction;
+    fn present(&mut self, board: &Board);
+
+    // Called with each action before the game is updated with it.
+    fn on_action(&mut self, _action: &SudokuAction) {
+    }
+
+    fn set_status(&mut self, _status: &GameStatus) {
+    }
+
+    fn set_clipboard_text(&mut self, _text: &str) {
+    }
+
+    fn print(&mut self, text: &str) {
+        print!("{}", text);
+    }
+}
+
+pub fn run<F: Frontend>(frontend: &mut F, game: &mut Game) {
+    let mut quit = false;
+
+    while !quit {
+        frontend.set_status(&game.status());
+        frontend.present(game.board());
+
+        match frontend.read_input() {
+            SudokuAction::Quit => quit = true,
+
+            SudokuAction::Export =>
+                for &format in [ExportFormat::Grid,
+                        ExportFormat::Line,
+                        ExportFormat::PencilMarks].iter() {
+                    frontend.print(&export::export(game.board(), format));
+                },
+
+            SudokuAction::Copy(format) => {
+                    let s = export::export(game.board(), format);
+                    frontend.set_clipboard_text(s.trim());
+                },
+
+            a => {
+                    frontend.on_action(&a);
+                    game.update(a);
+                }
+        }
+    }
+}
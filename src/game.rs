@@ -0,0 +1,452 @@
+// game.rs
+
+use std::io;
+
+use action::SudokuAction;
+use board::Board;
+use puzzle::PuzzleCollection;
+use savegame;
+use savegame::SaveGame;
+use solver;
+use solver::Grid;
+use stats;
+use stats::PuzzleStats;
+use stopwatch::Stopwatch;
+
+type History = Vec<Board>;
+
+pub struct Game {
+    h: History,
+    curr_history: usize,
+    puzzles: Option<PuzzleCollection>,
+    stopwatch: Stopwatch,
+    undos: usize,
+    autofills: usize,
+
+    // Some(solution) if the puzzle has exactly one
+    solution: Option<Grid>,
+
+    // mark mistakes after every move, or until the next move
+    check_mode: bool,
+    checked: bool,
+
+    // Some(result) while the completion screen is shown.  The board is
+    // locked until the player continues or starts a new game.
+    completion: Option<Completion>,
+
+    // fill in the forced values after every move
+    autofill_after_move: bool,
+
+    // Some(key) if the puzzle has givens
+    stats_key: Option<String>,
+    stats: Option<PuzzleStats>,
+
+    // false to only read the statistics, never write them
+    keep_stats: bool
+}
+
+// What a frontend shows besides the board.
+#[derive(Clone)]
+pub struct GameStatus {
+    // Some((index, count)) if playing from a puzzle collection
+    pub puzzle_position: Option<(usize,usize)>,
+
+    // milliseconds spent solving
+    pub elapsed: u64,
+
+    pub paused: bool,
+
+    pub completion: Option<Completion>,
+
+    // (x, y) of assignments that differ from the solution, while checking
+    pub mistakes: Vec<(u8,u8)>,
+    pub check_mode: bool,
+
+    // the record of the current puzzle
+    pub stats: Option<PuzzleStats>
+}
+
+#[derive(Clone,Copy)]
+pub struct Completion {
+    // milliseconds spent solving
+    pub elapsed: u64,
+
+    pub undos: usize,
+    pub autofills: usize
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game {
+            h: vec![Board::new()],
+            curr_history: 0,
+            puzzles: None,
+            stopwatch: Stopwatch::new(),
+            undos: 0,
+            autofills: 0,
+            solution: None,
+            check_mode: false,
+            checked: false,
+            completion: None,
+            autofill_after_move: false,
+            stats_key: None,
+            stats: None,
+            keep_stats: true
+        }
+    }
+
+    // A game that leaves the statistics alone, for replays: the moves are
+    // not the player's, and are timed by the replay.
+    pub fn without_stats() -> Game {
+        let mut g = Game::new();
+        g.keep_stats = false;
+        g
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.h[self.curr_history]
+    }
+
+    pub fn status(&self) -> GameStatus {
+        GameStatus {
+            puzzle_position: self.puzzles.as_ref().map(|c| (c.position(), c.len())),
+            elapsed: self.stopwatch.elapsed_ms(),
+            paused: self.stopwatch.is_paused() && self.completion.is_none(),
+            completion: self.completion,
+            mistakes:
+                if self.check_mode || self.checked {
+                    self.mistakes(self.board())
+                } else {
+                    Vec::new()
+                },
+            check_mode: self.check_mode,
+            stats: self.stats.clone()
+        }
+    }
+
+    fn mistakes(&self, board: &Board) -> Vec<(u8,u8)> {
+        let mut ms = Vec::new();
+
+        if let Some(ref solution) = self.solution {
+            let grid = solver::from_board(board, false);
+            for i in 0..81 {
+                if grid[i] != 0 && grid[i] != solution[i] {
+                    ms.push(((i % 9) as u8, (i / 9) as u8));
+                }
+            }
+        }
+
+        ms
+    }
+
+    pub fn load_puzzles(&mut self, c: PuzzleCollection) {
+        self.start(c.current());
+        self.puzzles = Some(c);
+    }
+
+    // Starts a new attempt at b.
+    fn start(&mut self, b: Board) {
+        self.reset(b);
+        self.update_stats(|s| s.attempts = s.attempts + 1);
+    }
+
+    fn reset(&mut self, b: Board) {
+        let givens = solver::from_board(&b, true);
+        self.solution =
+            if solver::has_unique_solution(&givens) {
+                solver::solve(&givens)
+            } else {
+                None
+            };
+        self.checked = false;
+
+        self.stats_key = stats::key(&b);
+        self.stats = None;
+        self.h.clear();
+        self.h.push(b);
+        self.curr_history = self.h.len() - 1;
+        self.stopwatch = Stopwatch::new();
+        self.undos = 0;
+        self.autofills = 0;
+        self.completion = None;
+    }
+
+    fn update_stats<F>(&mut self, f: F) where F: FnOnce(&mut PuzzleStats) {
+        if let Some(ref key) = self.stats_key {
+            if !self.keep_stats {
+                self.stats = stats::load().ok().and_then(|all| all.get(key).cloned());
+                return;
+            }
+
+            match stats::update(key, f) {
+                Ok(s) => self.stats = Some(s),
+                Err(e) => eprintln!("stats: {}", e)
+            }
+        }
+    }
+
+    fn load_game(&mut self, sg: io::Result<SaveGame>) {
+        match sg {
+            Ok(sg) => {
+                self.reset(sg.board);
+                self.update_stats(|_| {});
+                self.puzzles = None;
+                self.stopwatch = Stopwatch::with_elapsed(sg.elapsed);
+            },
+            Err(e) => eprintln!("load: {}", e)
+        }
+    }
+
+    pub fn update(&mut self, action: SudokuAction) {
+        let mut maybe_new_b: Option<Board> = None;
+        assert!(self.curr_history < self.h.len());
+
+        // the board is hidden while paused, and locked once solved
+        if self.stopwatch.is_paused() {
+            match action {
+                SudokuAction::Undo
+                | SudokuAction::Redo
+                | SudokuAction::AssignValue(..)
+                | SudokuAction::UnassignValue(..)
+                | SudokuAction::CrossOutValue(..)
+                | SudokuAction::ToggleCrossOut(..)
+                | SudokuAction::CrossOutMany(..)
+                | SudokuAction::ToggleCrossOutMany(..)
+                | SudokuAction::UnassignMany(..)
+                | SudokuAction::ColourTile(..)
+                | SudokuAction::ColourCandidate(..)
+                | SudokuAction::ClearColours
+                | SudokuAction::ToggleLink(..)
+                | SudokuAction::ClearLinks
+                | SudokuAction::AutoFill
+                | SudokuAction::CheckNow
+                | SudokuAction::RewindToCorrect => return,
+                SudokuAction::TogglePause
+                | SudokuAction::Pause if self.completion.is_some() => return,
+                _ => {}
+            }
+        }
+
+        let is_move = match action {
+            SudokuAction::AssignValue(..)
+            | SudokuAction::UnassignValue(..)
+            | SudokuAction::CrossOutValue(..)
+            | SudokuAction::ToggleCrossOut(..)
+            | SudokuAction::CrossOutMany(..)
+            | SudokuAction::ToggleCrossOutMany(..)
+            | SudokuAction::UnassignMany(..) => true,
+            _ => false
+        };
+
+        match action {
+            SudokuAction::NoOp
+            | SudokuAction::Quit
+            | SudokuAction::Export
+            | SudokuAction::Copy(_) => {},
+
+            SudokuAction::New(Some(filename)) =>
+                if let Some(c) = PuzzleCollection::load(&filename) {
+                    self.load_puzzles(c);
+                },
+
+            SudokuAction::NewFromString(contents) =>
+                if let Some(c) = PuzzleCollection::from_string("clipboard", &contents) {
+                    self.load_puzzles(c);
+                },
+
+            SudokuAction::New(None) => {
+                    self.start(Board::new());
+                    self.puzzles = None;
+                },
+
+            SudokuAction::NextPuzzle =>
+                if let Some(b) = self.puzzles.as_mut().and_then(|c| c.next()) {
+                    self.start(b);
+                },
+
+            SudokuAction::PrevPuzzle =>
+                if let Some(b) = self.puzzles.as_mut().and_then(|c| c.prev()) {
+                    self.start(b);
+                },
+
+            SudokuAction::GotoPuzzle(n) =>
+                if let Some(b) = self.puzzles.as_mut().and_then(|c| c.goto(n)) {
+                    self.start(b);
+                },
+
+            SudokuAction::Undo =>
+                if self.curr_history > 0 {
+                    self.curr_history = self.curr_history - 1;
+                    self.undos = self.undos + 1;
+                    self.checked = false;
+                },
+
+            SudokuAction::Redo =>
+                if self.curr_history + 1 < self.h.len() {
+                    self.curr_history = self.curr_history + 1;
+                    self.checked = false;
+                },
+
+            SudokuAction::AssignValue(x,y,v) =>
+                maybe_new_b = self.board().assign_value(x, y, v, false),
+
+            SudokuAction::UnassignValue(x,y) =>
+                maybe_new_b = self.board().unassign_value(x, y),
+
+            SudokuAction::CrossOutValue(x,y,v) =>
+                maybe_new_b = self.board().cross_out_value(x, y, v),
+
+            SudokuAction::ToggleCrossOut(x,y,v) =>
+                maybe_new_b = self.board().restore_value(x, y, v)
+                    .or_else(|| self.board().cross_out_value(x, y, v)),
+
+            SudokuAction::CrossOutMany(tiles, v) =>
+                maybe_new_b = apply_to_tiles(self.board(), &tiles,
+                        |b, x, y| b.cross_out_value(x, y, v)),
+
+            SudokuAction::ToggleCrossOutMany(tiles, v) =>
+                maybe_new_b = apply_to_tiles(self.board(), &tiles,
+                        |b, x, y| b.cross_out_value(x, y, v))
+                    .or_else(|| apply_to_tiles(self.board(), &tiles,
+                        |b, x, y| b.restore_value(x, y, v))),
+
+            SudokuAction::UnassignMany(tiles) =>
+                maybe_new_b = apply_to_tiles(self.board(), &tiles,
+                        |b, x, y| b.unassign_value(x, y)),
+
+            SudokuAction::ColourTile(x,y,c) =>
+                maybe_new_b = colour_tile(self.board(), x, y, c),
+
+            SudokuAction::ColourCandidate(x,y,v,c) => {
+                    let b = self.board();
+                    let c1 = if b.candidate_colour(x, y, v) == c { 0 } else { c };
+                    maybe_new_b = b.colour_candidate(x, y, v, c1)
+                        .or_else(|| colour_tile(b, x, y, c));
+                },
+
+            SudokuAction::ClearColours =>
+                maybe_new_b = self.board().clear_colours(),
+
+            SudokuAction::ToggleLink(a,b,strong) =>
+                maybe_new_b = self.board().toggle_link(a, b, strong),
+
+            SudokuAction::ClearLinks =>
+                maybe_new_b = self.board().clear_links(),
+
+            SudokuAction::AutoFill => {
+                    maybe_new_b = self.board().autofill();
+                    if maybe_new_b.is_some() {
+                        self.autofills = self.autofills + 1;
+                        self.update_stats(|s| s.hints = s.hints + 1);
+                    }
+                },
+
+            SudokuAction::SetAutoFillAfterMove(on) =>
+                self.autofill_after_move = on,
+
+            SudokuAction::ToggleCheckMode =>
+                self.check_mode = !self.check_mode,
+
+            SudokuAction::CheckNow =>
+                if self.solution.is_some() {
+                    self.checked = true;
+                    self.update_stats(|s| s.hints = s.hints + 1);
+                },
+
+            // walk back to the last position without mistakes, keeping the
+            // later positions for redo
+            SudokuAction::RewindToCorrect =>
+                if self.solution.is_some() {
+                    let mut i = self.curr_history;
+                    while i > 0 && !self.mistakes(&self.h[i]).is_empty() {
+                        i = i - 1;
+                    }
+
+                    if i != self.curr_history {
+                        self.curr_history = i;
+                        self.checked = false;
+                        self.update_stats(|s| s.hints = s.hints + 1);
+                    }
+                },
+
+            SudokuAction::TogglePause =>
+                if self.stopwatch.is_paused() {
+                    self.stopwatch.resume();
+                } else {
+                    self.stopwatch.pause();
+                },
+
+            SudokuAction::Pause =>
+                self.stopwatch.pause(),
+
+            SudokuAction::Continue =>
+                if self.completion.is_some() {
+                    self.completion = None;
+                    self.stopwatch.resume();
+                },
+
+            SudokuAction::SaveGame =>
+                if let Err(e) = savegame::save(self.board(), self.stopwatch.elapsed_ms()) {
+                    eprintln!("save: {}", e);
+                },
+
+            SudokuAction::LoadGame =>
+                self.load_game(savegame::load()),
+
+            SudokuAction::LoadFromString(contents) =>
+                self.load_game(savegame::parse(&contents))
+        }
+
+        // the forced values are filled in as part of the move, and count as
+        // an auto-fill
+        if self.autofill_after_move && is_move {
+            if let Some(filled) = maybe_new_b.as_ref().and_then(|b| b.autofill()) {
+                maybe_new_b = Some(filled);
+                self.autofills = self.autofills + 1;
+                self.update_stats(|s| s.hints = s.hints + 1);
+            }
+        }
+
+        if let Some(new_b) = maybe_new_b {
+            let was_solved = self.board().is_solved();
+
+            while self.h.len() > self.curr_history + 1 {
+                self.h.pop();
+            }
+            self.h.push(new_b);
+            self.curr_history = self.h.len() - 1;
+            self.checked = false;
+
+            if !was_solved && self.board().is_solved() {
+                let elapsed = self.stopwatch.elapsed_ms();
+                self.stopwatch.pause();
+                self.completion = Some(Completion {
+                    elapsed: elapsed,
+                    undos: self.undos,
+                    autofills: self.autofills
+                });
+
+                self.update_stats(|s| {
+                    s.completions = s.completions + 1;
+                    s.best = Some(s.best.map_or(elapsed, |ms| ms.min(elapsed)));
+                });
+            }
+        }
+    }
+}
+
+// Painting a tile with its own colour clears it.
+fn colour_tile(board: &Board, x: u8, y: u8, c: u8) -> Option<Board> {
+    let c1 = if board.tile_colour(x, y) == c { 0 } else { c };
+    board.colour_tile(x, y, c1)
+}
+
+// Applies f to each tile, skipping the tiles it does not apply to.  None if
+// nothing changed.
+fn apply_to_tiles<F>(board: &Board, tiles: &Vec<(u8,u8)>, f: F) -> Option<Board>
+        where F: Fn(&Board, u8, u8) -> Option<Board> {
+    tiles.iter().fold(None, |maybe_b, &(x, y)| {
+            let b = maybe_b.as_ref().unwrap_or(board);
+            f(b, x, y).or(maybe_b)
+        })
+}
@@ -0,0 +1,51 @@
+// generator.rs
+
+use rating::{Difficulty,rate};
+use solver::{Grid,Rng,has_unique_solution,random_solution};
+
+const MAX_ATTEMPTS: u32 = 1000;
+
+// Generates a puzzle with a unique solution and the given difficulty.
+// Returns None if no such puzzle was found after many attempts.
+pub fn generate(difficulty: Difficulty, rng: &mut Rng) -> Option<Grid> {
+    for _ in 0..MAX_ATTEMPTS {
+        let mut puzzle = random_solution(rng);
+        let mut order: Vec<usize> = (0..81).collect();
+        rng.shuffle(&mut order);
+
+        // remove clues while the puzzle remains unique and no harder
+        // than requested.
+        for i in order {
+            let v = puzzle[i];
+            puzzle[i] = 0;
+
+            if !has_unique_solution(&puzzle) || rate(&puzzle) > difficulty {
+                puzzle[i] = v;
+            }
+        }
+
+        if rate(&puzzle) == difficulty {
+            return Some(puzzle);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate;
+    use rating::{Difficulty,rate};
+    use solver::{Rng,has_unique_solution,is_consistent};
+
+    #[test]
+    fn generated_puzzles_are_unique_and_rated() {
+        let mut rng = Rng::new(1);
+        for &d in [Difficulty::Easy, Difficulty::Medium].iter() {
+            let puzzle = generate(d, &mut rng).unwrap();
+            assert!(is_consistent(&puzzle));
+            assert!(has_unique_solution(&puzzle));
+            assert_eq!(rate(&puzzle), d);
+        }
+    }
+}
@@ -1,20 +1,48 @@
 // resource.rs
 
+use std::cmp::max;
 use std::collections::HashMap;
-use std::env;
-use std::path::Path;
+use std::path::{Path,PathBuf};
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
-use sdl2::render::{Renderer,Texture};
+use sdl2::render::{BlendMode,Renderer,Texture};
+
+use anim::{AnimKind,ANIM_KINDS};
+use atlas::{Atlas,Metrics};
+use paths;
+use theme::Theme;
+use ttf::TtfFont;
 
+#[cfg(feature = "flic")]
+use std::fs;
 #[cfg(feature = "flic")]
 use flic;
 #[cfg(feature = "flic")]
-use sdl2::pixels::PixelFormatEnum;
+use flic::codec::{FLI_COPY,decode_chunk};
 #[cfg(feature = "flic")]
-use sdl2::render::BlendMode;
+use flic::flic::{FCID_FRAME,FCID_PREFIX,FLIH_MAGIC,FLIHR_MAGIC,SIZE_OF_CHUNK,
+    SIZE_OF_FLIC_FRAME,SIZE_OF_FLIC_HEADER};
 
 #[cfg(feature = "png")]
-use sdl2::image::LoadTexture;
+use sdl2::image::{ImageRWops,LoadSurface};
+#[cfg(feature = "png")]
+use sdl2::rwops::RWops;
+#[cfg(feature = "png")]
+use sdl2::surface::Surface;
+
+// the default graphics, for when there are no files to override them
+#[cfg(feature = "flic")]
+const SUDOKU_FLC: &'static [u8] = include_bytes!("../resource/sudoku.flc");
+#[cfg(feature = "flic")]
+const POP_FLC: &'static [u8] = include_bytes!("../resource/pop.flc");
+#[cfg(feature = "flic")]
+const SHAKE_FLC: &'static [u8] = include_bytes!("../resource/shake.flc");
+#[cfg(feature = "flic")]
+const SWEEP_FLC: &'static [u8] = include_bytes!("../resource/sweep.flc");
+#[cfg(feature = "flic")]
+const WIN_FLC: &'static [u8] = include_bytes!("../resource/win.flc");
+#[cfg(feature = "png")]
+const SUDOKU_PNG: &'static [u8] = include_bytes!("../resource/sudoku.png");
 
 #[derive(Clone,Copy,Eq,Hash,PartialEq)]
 pub enum Res {
@@ -32,180 +60,499 @@ pub enum Res {
     TileConflict(u8),
 }
 
-pub const TILE_NUMBER_HEIGHT: u32 = 15;
-pub const TILE_NUMBER_WIDTH: u32 = 15;
-pub const TOOLBAR_BUTTON_HEIGHT: u32 = 9;
-pub const TOOLBAR_BUTTON_WIDTH: u32 = 53;
-pub const TOOLBAR_NUMBER_WIDTH: u32 = 13;
-pub const TOOLBAR_UNDO_REDO_WIDTH: u32 = 8;
-
 pub struct GfxLib<'a> {
     pub renderer: Renderer<'a>,
+    pub theme: Theme,
     texture: Texture,
-    lib: HashMap<Res, Rect>,
-}
 
-impl<'a> GfxLib<'a> {
-    pub fn new(renderer: Renderer<'a>) -> GfxLib<'a> {
-        let texture = match GfxLib::load_texture(&renderer) {
-            None => panic!("Error loading sudoku.flc or sudoku.png"),
-            Some(t) => t
-        };
+    // the sprites in white with their black outlines, to be drawn in the
+    // theme's number colours
+    mask: Texture,
 
-        let mut lib = HashMap::new();
+    // Some(font) to draw the numbers and text with, rather than the
+    // sprites and pixel fonts
+    pub font: Option<TtfFont>,
 
-        lib.insert(Res::ToolbarSudoku,
-                Rect::new( 0,  0, TOOLBAR_BUTTON_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-        lib.insert(Res::ToolbarUndo,
-                Rect::new(54,  0, TOOLBAR_UNDO_REDO_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-        lib.insert(Res::ToolbarRedo,
-                Rect::new(64,  0, TOOLBAR_UNDO_REDO_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-        lib.insert(Res::ToolbarActivePencil,
-                Rect::new( 0, 10, TOOLBAR_BUTTON_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-        lib.insert(Res::ToolbarInactivePencil,
-                Rect::new( 0, 20, TOOLBAR_BUTTON_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-        lib.insert(Res::ToolbarActiveCrossOut,
-                Rect::new(54, 10, TOOLBAR_BUTTON_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-        lib.insert(Res::ToolbarInactiveCrossOut,
-                Rect::new(54, 20, TOOLBAR_BUTTON_WIDTH, TOOLBAR_BUTTON_HEIGHT));
+    atlas: Atlas,
+    pub metrics: Metrics,
 
-        for v in 1..9+1 {
-            let x = ((TOOLBAR_NUMBER_WIDTH - 1) as i32) * ((v - 1) as i32);
+    anims: HashMap<AnimKind, Animation>
+}
 
-            lib.insert(Res::ToolbarActiveNumber(v),
-                    Rect::new(x, 30, TOOLBAR_NUMBER_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-            lib.insert(Res::ToolbarInactiveNumber(v),
-                    Rect::new(x, 40, TOOLBAR_NUMBER_WIDTH, TOOLBAR_BUTTON_HEIGHT));
-        }
+// The sprite sheet as RGBA bytes, four to a pixel.
+struct Image {
+    w: usize,
+    h: usize,
+    pixels: Vec<u8>
+}
 
-        for v in 1..9+1 {
-            let x = (TILE_NUMBER_WIDTH * (v - 1)) as i32;
+// The frames of a FLIC, and the milliseconds each is shown for.
+type Frames = (Vec<Image>, u32);
 
-            lib.insert(Res::TileInit(v as u8),
-                    Rect::new(x, 50, TILE_NUMBER_WIDTH, TILE_NUMBER_HEIGHT));
-            lib.insert(Res::TileGuess(v as u8),
-                    Rect::new(x, 65, TILE_NUMBER_WIDTH, TILE_NUMBER_HEIGHT));
-            lib.insert(Res::TileConflict(v as u8),
-                    Rect::new(x, 80, TILE_NUMBER_WIDTH, TILE_NUMBER_HEIGHT));
-        }
+// The frames of an animation, w x h pixels each, in rows of cols frames.
+struct Animation {
+    texture: Texture,
+    w: u32,
+    h: u32,
+    cols: u32,
+    frames: u32,
+    frame_ms: u32
+}
+
+impl<'a> GfxLib<'a> {
+    // resource_dirs are the directories to look for the graphics in, see
+    // paths::resource_dirs.
+    pub fn new(renderer: Renderer<'a>, resource_dirs: &[PathBuf], theme: Theme) -> GfxLib<'a> {
+        let (image, atlas_path) = match GfxLib::load_image(resource_dirs) {
+            None => panic!("Error loading sudoku.flc or sudoku.png"),
+            Some(image) => image
+        };
+
+        let (texture, mask) =
+            match (make_texture(&renderer, &image, false), make_texture(&renderer, &image, true)) {
+                (Some(t), Some(m)) => (t, m),
+                _ => panic!("Error creating sprite textures")
+            };
+
+        let atlas = Atlas::load(atlas_path.as_ref().map(|p| p.as_path()));
+        let anims = load_anims(&renderer, resource_dirs);
 
         GfxLib {
             renderer: renderer,
+            theme: theme,
             texture: texture,
-            lib: lib
+            mask: mask,
+            font: None,
+            metrics: atlas.metrics(),
+            atlas: atlas,
+            anims: anims
         }
     }
 
-    fn load_texture(renderer: &Renderer<'a>) -> Option<Texture> {
-        if let Some(t) = try_load_flic(renderer) {
-            return Some(t);
+    // Files first, then the copies built in.  A file comes with the path
+    // of its atlas, sudoku.atlas in the same directory.
+    fn load_image(resource_dirs: &[PathBuf]) -> Option<(Image, Option<PathBuf>)> {
+        let atlas_path = |path: PathBuf| path.with_file_name("sudoku.atlas");
+
+        if let Some(((mut frames, _), path)) = try_load_flic("sudoku.flc", resource_dirs) {
+            return Some((frames.remove(0), Some(atlas_path(path))));
+        }
+        if let Some((image, path)) = try_load_png(resource_dirs) {
+            return Some((image, Some(atlas_path(path))));
         }
-        if let Some(t) = try_load_png(renderer) {
-            return Some(t);
+        if let Some((mut frames, _)) = try_load_embedded_flic("sudoku.flc") {
+            return Some((frames.remove(0), None));
+        }
+        if let Some(image) = try_load_embedded_png() {
+            return Some((image, None));
         }
 
         None
     }
 
+    // Tile numbers are drawn in the font if there is one, or in the theme's
+    // colours if it tints them, and everything else is tinted.
     pub fn draw(&mut self, res: Res, dst: Rect) {
-        if let Some(&src) = self.lib.get(&res) {
-            let _ = self.renderer.copy(&self.texture, Some(src), Some(dst));
+        let (init, guess, conflict) = self.theme.numbers;
+        let number = match res {
+            Res::TileInit(v) => Some((v, init)),
+            Res::TileGuess(v) => Some((v, guess)),
+            Res::TileConflict(v) => Some((v, conflict)),
+            _ => None
+        };
+
+        if let (Some((v, c)), Some(ref mut font)) = (number, self.font.as_mut()) {
+            let centre = (dst.x() + (dst.width() / 2) as i32, dst.y() + (dst.height() / 2) as i32);
+            font.draw_char(&mut self.renderer, (b'0' + v) as char, centre, dst.height(), c);
+            return;
+        }
+
+        if let Some(src) = self.atlas.get(res) {
+            let number_colour = match number {
+                Some((_, c)) if self.theme.tint_numbers => Some(c),
+                _ => None
+            };
+
+            if let Some(c) = number_colour {
+                let (r, g, b) = c.rgb();
+                self.mask.set_color_mod(r, g, b);
+                let _ = self.renderer.copy(&self.mask, Some(src), Some(dst));
+            } else {
+                let (r, g, b) = self.theme.sprite_tint.rgb();
+                self.texture.set_color_mod(r, g, b);
+                let _ = self.renderer.copy(&self.texture, Some(src), Some(dst));
+            }
         }
     }
-}
 
-/*--------------------------------------------------------------*/
+    // Some((frames, milliseconds per frame)) of an animation, or None if
+    // there are no frames for it.
+    pub fn anim_length(&self, kind: AnimKind) -> Option<(u32, u32)> {
+        self.anims.get(&kind).map(|a| (a.frames, a.frame_ms))
+    }
 
-#[cfg(not(feature = "flic"))]
-fn try_load_flic(_: &Renderer) -> Option<Texture> {
-    None
+    // Draws a frame of an animation at scale, centred on dst.
+    pub fn draw_anim(&mut self, kind: AnimKind, frame: u32, dst: Rect, scale: u32) {
+        if let Some(a) = self.anims.get(&kind) {
+            if frame >= a.frames {
+                return;
+            }
+
+            let src = Rect::new(
+                    (a.w * (frame % a.cols)) as i32,
+                    (a.h * (frame / a.cols)) as i32,
+                    a.w, a.h);
+            let (w, h) = (a.w * scale, a.h * scale);
+            let _ = self.renderer.copy(&a.texture, Some(src), Some(Rect::new(
+                    dst.x() + (dst.width() as i32 - w as i32) / 2,
+                    dst.y() + (dst.height() as i32 - h as i32) / 2,
+                    w, h)));
+        }
+    }
 }
 
-#[cfg(feature = "flic")]
-fn try_load_flic(renderer: &Renderer) -> Option<Texture> {
-    let path = Path::new("resource/sudoku.flc");
-    if let Some(t) = try_load_flic2(renderer, &path) {
-        return Some(t);
+// mask is true to make every pixel but black ones white.
+fn make_texture(renderer: &Renderer, image: &Image, mask: bool) -> Option<Texture> {
+    let mut texture = match renderer.create_texture_streaming(
+            PixelFormatEnum::ABGR8888, image.w as u32, image.h as u32) {
+        Ok(t) => t,
+        Err(_) => return None
+    };
+
+    let res = texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
+        for y in 0..image.h {
+            for x in 0..image.w {
+                let src = 4 * (image.w * y + x);
+                let dst = pitch * y + 4 * x;
+                let black = image.pixels[src..src + 3].iter().all(|&c| c == 0);
+
+                for i in 0..3 {
+                    buffer[dst + i] =
+                        if mask && !black { 255 } else { image.pixels[src + i] };
+                }
+                buffer[dst + 3] = image.pixels[src + 3];
+            }
+        }
+    });
+    if res.is_err() {
+        return None;
     }
 
-    if let Ok(mut path) = env::current_exe() {
-        path.set_file_name("sudoku.flc");
-        if let Some(t) = try_load_flic2(renderer, &path) {
-            return Some(t);
+    texture.set_blend_mode(BlendMode::Blend);
+    Some(texture)
+}
+
+// Some(font) from the font setting if given, otherwise sudoku.ttf in the
+// resource directories if there is one.
+pub fn load_font(font_path: Option<&Path>, resource_dirs: &[PathBuf]) -> Option<TtfFont> {
+    if let Some(path) = font_path {
+        let font = TtfFont::load(path);
+        if font.is_none() {
+            eprintln!("{}: font not loaded", path.display());
         }
+        return font;
     }
 
-    None
+    paths::resource_candidates("sudoku.ttf", resource_dirs).iter()
+        .filter(|path| path.exists())
+        .filter_map(|path| TtfFont::load(path))
+        .next()
 }
 
-#[cfg(feature = "flic")]
-fn try_load_flic2(renderer: &Renderer, path: &Path) -> Option<Texture> {
-    if let Ok(mut f) = flic::FlicFile::open(path) {
-        let w = f.width() as usize;
-        let h = f.height() as usize;
-        let mut buf = vec![0; w * h];
-        let mut pal = [0; 3 * 256];
+// The animations from files, or the copies built in.  Without the flic
+// feature there are none.
+fn load_anims(renderer: &Renderer, resource_dirs: &[PathBuf]) -> HashMap<AnimKind, Animation> {
+    let mut anims = HashMap::new();
 
-        let res = f.read_next_frame(
-                &mut flic::RasterMut::new(w, h, &mut buf, &mut pal));
-        if res.is_err() {
-            return None;
-        }
+    for &kind in ANIM_KINDS.iter() {
+        let name = kind.file_name();
+        let frames = try_load_flic(name, resource_dirs).map(|(frames, _)| frames)
+            .or_else(|| try_load_embedded_flic(name));
 
-        let texture = renderer.create_texture_streaming(
-                PixelFormatEnum::ABGR8888, w as u32, h as u32);
-        if texture.is_err() {
-            return None;
+        if let Some(a) = frames.and_then(|f| make_animation(renderer, f)) {
+            anims.insert(kind, a);
         }
+    }
 
-        let mut t = texture.unwrap();
-        render_to_texture(&mut t, w, h, &buf, &pal);
-        t.set_blend_mode(BlendMode::Blend);
-        return Some(t);
+    anims
+}
+
+// Lays the frames out in a square, rather than one long row that may be
+// wider than the largest texture.
+fn make_animation(renderer: &Renderer, (frames, frame_ms): Frames) -> Option<Animation> {
+    let (w, h) = match frames.first() {
+        Some(f) => (f.w, f.h),
+        None => return None
+    };
+    let n = frames.len();
+    let cols = (1..n + 1).find(|c| c * c >= n).unwrap_or(1);
+    let rows = (n + cols - 1) / cols;
+
+    let mut sheet = Image { w: w * cols, h: h * rows, pixels: vec![0; 4 * w * cols * h * rows] };
+    for (i, f) in frames.iter().enumerate() {
+        let (x0, y0) = (w * (i % cols), h * (i / cols));
+        for y in 0..h {
+            let dst = 4 * (sheet.w * (y0 + y) + x0);
+            sheet.pixels[dst..dst + 4 * w].copy_from_slice(&f.pixels[4 * w * y..4 * w * (y + 1)]);
+        }
     }
 
+    make_texture(renderer, &sheet, false).map(|texture| Animation {
+        texture: texture,
+        w: w as u32,
+        h: h as u32,
+        cols: cols as u32,
+        frames: n as u32,
+        frame_ms: max(frame_ms, 1)
+    })
+}
+
+/*--------------------------------------------------------------*/
+
+#[cfg(not(feature = "flic"))]
+fn try_load_flic(_: &str, _: &[PathBuf]) -> Option<(Frames, PathBuf)> {
     None
 }
 
+#[cfg(not(feature = "flic"))]
+fn try_load_embedded_flic(_: &str) -> Option<Frames> {
+    None
+}
+
+// A file that is there but cannot be read is reported, and skipped.
 #[cfg(feature = "flic")]
-fn render_to_texture(
-        texture: &mut Texture,
-        w: usize, h: usize, buf: &[u8], pal: &[u8]) {
-    texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
-        for y in 0..h {
-            for x in 0..w {
-                let offset = pitch * y + 4 * x;
-                let c = buf[w * y + x] as usize;
-
-                buffer[offset + 0] = pal[3 * c + 0];
-                buffer[offset + 1] = pal[3 * c + 1];
-                buffer[offset + 2] = pal[3 * c + 2];
-                buffer[offset + 3] = if c == 0 { 0 } else { 255 };
+fn try_load_flic(name: &str, resource_dirs: &[PathBuf]) -> Option<(Frames, PathBuf)> {
+    paths::resource_candidates(name, resource_dirs).into_iter()
+        .filter(|path| path.exists())
+        .filter_map(|path| match load_flic_file(&path) {
+                Ok(frames) => Some((frames, path)),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    None
+                }
+            })
+        .next()
+}
+
+#[cfg(feature = "flic")]
+fn load_flic_file(path: &Path) -> Result<Frames, String> {
+    fs::read(path).map_err(|e| e.to_string())
+        .and_then(|data| decode_flic(&data))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(feature = "flic")]
+fn try_load_embedded_flic(name: &str) -> Option<Frames> {
+    let data = match name {
+        "sudoku.flc" => SUDOKU_FLC,
+        "pop.flc" => POP_FLC,
+        "shake.flc" => SHAKE_FLC,
+        "sweep.flc" => SWEEP_FLC,
+        "win.flc" => WIN_FLC,
+        _ => return None
+    };
+
+    match decode_flic(data) {
+        Ok(frames) => Some(frames),
+        Err(e) => {
+            eprintln!("{} (built in): {}", name, e);
+            None
+        }
+    }
+}
+
+// Every frame, with palette index 0 transparent.  FlicFile only reads
+// files, so the headers are read here, and the chunks decoded by the
+// flic crate.  Every offset is checked against the length of the data.
+#[cfg(feature = "flic")]
+fn decode_flic(data: &[u8]) -> Result<Frames, String> {
+    let u16_at = |i: usize| data.get(i..i + 2)
+        .map(|b| b[0] as u16 | (b[1] as u16) << 8)
+        .ok_or_else(|| format!("truncated at byte {}", i));
+    let u32_at = |i: usize| u16_at(i).and_then(
+            |lo| u16_at(i + 2).map(|hi| lo as u32 | (hi as u32) << 16));
+
+    let frame_count = u16_at(6)? as usize;
+    let w = u16_at(8)? as usize;
+    let h = u16_at(10)? as usize;
+    let frame_ms = match u16_at(4)? {
+        FLIHR_MAGIC => u32_at(16)?,
+
+        // FLI files count in 70ths of a second
+        FLIH_MAGIC => u16_at(16)? as u32 * 1000 / 70,
+
+        _ => return Err(String::from("not a FLIC file"))
+    };
+
+    if w == 0 || h == 0 || frame_count == 0 {
+        return Err(format!("empty: {}x{}, {} frames", w, h, frame_count));
+    }
+
+    let mut buf = vec![0; w * h];
+    let mut pal = [0; 3 * 256];
+    let mut frames = Vec::new();
+    let mut offset = SIZE_OF_FLIC_HEADER;
+
+    // a prefix chunk may come before the first frame
+    if u16_at(offset + 4)? == FCID_PREFIX {
+        offset = offset + u32_at(offset)? as usize;
+    }
+
+    // each frame changes the one before
+    for n in 0..frame_count {
+        let size = u32_at(offset)? as usize;
+        if u16_at(offset + 4)? != FCID_FRAME || size < SIZE_OF_FLIC_FRAME {
+            return Err(format!("frame {}: bad frame header at byte {}", n, offset));
+        }
+
+        let mut chunk = offset + SIZE_OF_FLIC_FRAME;
+        for _ in 0..u16_at(offset + 6)? {
+            let chunk_size = u32_at(chunk)? as usize;
+            let magic = u16_at(chunk + 4)?;
+            if chunk_size < SIZE_OF_CHUNK {
+                return Err(format!("frame {}: bad chunk at byte {}", n, chunk));
             }
+
+            // Animator wrote FLI_COPY chunks 2 bytes short
+            let data_size = if magic == FLI_COPY && chunk_size == w * h + 4 {
+                w * h
+            } else {
+                chunk_size - SIZE_OF_CHUNK
+            };
+
+            let start = chunk + SIZE_OF_CHUNK;
+            let chunk_data = data.get(start..start + data_size)
+                .ok_or_else(|| format!("frame {}: truncated chunk at byte {}", n, chunk))?;
+            decode_chunk(magic, chunk_data, &mut flic::RasterMut::new(w, h, &mut buf, &mut pal))
+                .map_err(|e| format!("frame {}: chunk at byte {}: {}", n, chunk, e))?;
+
+            chunk = chunk + chunk_size;
+        }
+
+        let mut pixels = vec![0; 4 * w * h];
+        for (i, &c) in buf.iter().enumerate() {
+            let c = c as usize;
+
+            pixels[4 * i + 0] = pal[3 * c + 0];
+            pixels[4 * i + 1] = pal[3 * c + 1];
+            pixels[4 * i + 2] = pal[3 * c + 2];
+            pixels[4 * i + 3] = if c == 0 { 0 } else { 255 };
         }
-    }).unwrap();
+
+        frames.push(Image { w: w, h: h, pixels: pixels });
+        offset = offset + size;
+    }
+
+    Ok((frames, frame_ms))
 }
 
 /*--------------------------------------------------------------*/
 
 #[cfg(not(feature = "png"))]
-fn try_load_png(_: &Renderer) -> Option<Texture> {
+fn try_load_png(_: &[PathBuf]) -> Option<(Image, PathBuf)> {
     None
 }
 
+#[cfg(not(feature = "png"))]
+fn try_load_embedded_png() -> Option<Image> {
+    None
+}
+
+#[cfg(feature = "png")]
+fn try_load_png(resource_dirs: &[PathBuf]) -> Option<(Image, PathBuf)> {
+    paths::resource_candidates("sudoku.png", resource_dirs).into_iter()
+        .filter_map(|path| Surface::from_file(&path).ok()
+                .and_then(surface_to_image).map(|image| (image, path)))
+        .next()
+}
+
+#[cfg(feature = "png")]
+fn try_load_embedded_png() -> Option<Image> {
+    let rw = match RWops::from_bytes(SUDOKU_PNG) {
+        Ok(rw) => rw,
+        Err(_) => return None
+    };
+    rw.load().ok().and_then(surface_to_image)
+}
+
+// Converts whatever format the image is in to RGBA by blitting it.
 #[cfg(feature = "png")]
-fn try_load_png(renderer: &Renderer) -> Option<Texture> {
-    let path = Path::new("resource/sudoku.png");
-    if let Ok(t) = renderer.load_texture(&path) {
-        return Some(t);
+fn surface_to_image(mut src: Surface) -> Option<Image> {
+    let (w, h) = (src.width(), src.height());
+
+    let mut dst = match Surface::new(w, h, PixelFormatEnum::ABGR8888) {
+        Ok(s) => s,
+        Err(_) => return None
+    };
+    let _ = src.set_blend_mode(BlendMode::None);
+    if src.blit(None, &mut dst, None).is_err() {
+        return None;
     }
 
-    if let Ok(mut path) = env::current_exe() {
-        path.set_file_name("sudoku.png");
-        if let Ok(t) = renderer.load_texture(&path) {
-            return Some(t);
+    let (w, h) = (w as usize, h as usize);
+    let pitch = dst.pitch() as usize;
+    let pixels = dst.with_lock(|buffer: &[u8]| {
+        let mut pixels = Vec::with_capacity(4 * w * h);
+        for y in 0..h {
+            pixels.extend_from_slice(&buffer[pitch * y..pitch * y + 4 * w]);
+        }
+        pixels
+    });
+
+    Some(Image { w: w, h: h, pixels: pixels })
+}
+
+#[cfg(all(test, feature = "flic"))]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use super::*;
+
+    const EMBEDDED: [(&'static str, &'static [u8]); 5] = [
+        ("sudoku.flc", SUDOKU_FLC), ("pop.flc", POP_FLC), ("shake.flc", SHAKE_FLC),
+        ("sweep.flc", SWEEP_FLC), ("win.flc", WIN_FLC)];
+
+    #[test]
+    fn embedded_flics_decode() {
+        for &(name, data) in EMBEDDED.iter() {
+            let (frames, frame_ms) = decode_flic(data).expect(name);
+            let frame_count = data[6] as usize | (data[7] as usize) << 8;
+            assert_eq!(frames.len(), frame_count, "{}", name);
+            assert!(frame_ms > 0, "{}", name);
+
+            let (w, h) = (frames[0].w, frames[0].h);
+            assert!(frames.iter().all(|f| (f.w, f.h) == (w, h) && f.pixels.len() == 4 * w * h),
+                    "{}", name);
         }
     }
 
-    None
+    #[test]
+    fn truncated_flic_is_an_error() {
+        // the frames end before the ring frame, which is not decoded
+        let u32_at = |i: usize| (0..4).fold(0, |v, j| v | (POP_FLC[i + j] as usize) << (8 * j));
+        let end = (0..POP_FLC[6]).fold(SIZE_OF_FLIC_HEADER, |offset, _| offset + u32_at(offset));
+
+        for len in 0..end {
+            match decode_flic(&POP_FLC[..len]) {
+                Err(e) => assert!(e.contains("truncated"), "{}: {}", len, e),
+                Ok(_) => panic!("{} bytes decoded", len)
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_file_names_the_file() {
+        let path = env::temp_dir().join(format!("play_sudoku_test_{}.flc", process::id()));
+        let mut data = POP_FLC.to_vec();
+        data[4] = 0;
+        fs::write(&path, &data).unwrap();
+
+        let res = load_flic_file(&path);
+        let _ = fs::remove_file(&path);
+        match res {
+            Err(e) => assert_eq!(e, format!("{}: not a FLIC file", path.display())),
+            Ok(_) => panic!("malformed file decoded")
+        }
+    }
 }
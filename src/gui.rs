@@ -4,49 +4,61 @@ use std::cmp::{max,min};
 use sdl2;
 use sdl2::EventPump;
 use sdl2::TimerSubsystem;
+use sdl2::clipboard::ClipboardUtil;
+use sdl2::hint;
 use sdl2::event::Event;
 use sdl2::event::WindowEvent;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{KeyboardUtil,Keycode,Mod};
 use sdl2::mouse::MouseButton;
-use sdl2::pixels::Color;
+use sdl2::pixels::{Color,PixelFormatEnum};
 use sdl2::rect::Rect;
+use sdl2::render::Texture;
 use sdl2::video::FullscreenType;
 
 use action::SudokuAction;
-use board::Board;
+use anim;
+use anim::{Playing,Target};
+use atlas::Metrics;
+use bindings::{Bindings,Command,Input,Modifiers};
+use board::{Board,Link,NUM_COLOURS};
+use export::ExportFormat;
+use font::{GLYPH_WIDTH,GLYPH_HEIGHT,glyph};
+use frontend::{Brush,Frontend};
+use game::{Completion,GameStatus};
 use gfx::*;
+use paths;
+use settings::Settings;
+use sound;
+use sound::{Sound,Sounds};
+use stats::PuzzleStats;
+use stopwatch::format_ms;
+use theme;
+use theme::Theme;
 use tile::Tile;
 
 // FIXME - not sure what to import.
 const SDL_WINDOW_FULLSCREEN_DESKTOP: u32 = 0x1001;
 
-const MIN_TOOLBAR_WIDTH: u32
-    = 3
-    + TOOLBAR_UNDO_REDO_WIDTH + 2 // undo
-    + TOOLBAR_UNDO_REDO_WIDTH + 2 // redo
-    + TOOLBAR_BUTTON_WIDTH + 2 // pencil
-    + TOOLBAR_BUTTON_WIDTH + 2 // erase
-    + (TOOLBAR_NUMBER_WIDTH - 1) * 9 + 1 // numbers
-    + 3;
-
-const DEFAULT_SCREEN_WIDTH: u32 = 640;
-const DEFAULT_SCREEN_HEIGHT: u32 = 400;
-const MIN_SCREEN_WIDTH: u32 = MIN_TOOLBAR_WIDTH;
+// timer digits are drawn as seven-segment glyphs, 3x7 pixels each
+const SEGMENT_DIGIT_WIDTH: u32 = 4;
+const SEGMENT_COLON_WIDTH: u32 = 2;
+const TOOLBAR_TIMER_WIDTH: u32
+    = SEGMENT_DIGIT_WIDTH * 6 + SEGMENT_COLON_WIDTH * 2; // 00:00:00
+
+// colour brush swatches
+const TOOLBAR_SWATCH_WIDTH: u32 = 7;
+const TOOLBAR_PALETTE_WIDTH: u32 = (TOOLBAR_SWATCH_WIDTH + 1) * NUM_COLOURS as u32 - 1;
+
 const MIN_SCREEN_HEIGHT: u32 = 200;
 
 // (w, h, board_scale, toolbar_scale)
 type ScreenSize = (u32,u32,u32,u32);
 
-#[derive(Clone,Copy,Eq,PartialEq)]
-enum Brush {
-    Pencil,
-    CrossOut
-}
-
 enum WidgetType {
     Label,
     Undo,
     Redo,
+    Timer,
 
     // Tile(x,y)
     Tile(u8,u8),
@@ -55,27 +67,104 @@ enum WidgetType {
     ToolbarBrush(Brush,Res,Res),
 
     // ToolbarNumber(value)
-    ToolbarNumber(u8)
+    ToolbarNumber(u8),
+
+    // ToolbarColour(colour)
+    ToolbarColour(u8)
 }
 
 pub struct Gui<'a> {
     gfx: GfxLib<'a>,
     timer: TimerSubsystem,
     event_pump: EventPump,
+    clipboard: ClipboardUtil,
+    keyboard: KeyboardUtil,
+    bindings: Bindings,
     state: GuiState,
     widgets: Vec<Widget>,
 
+    // the layout size, which is the drawable size of the window divided
+    // by the render scale
     screen_size: ScreenSize,
+
+    // the window size in points, as mouse positions are given
+    window_size: (u32,u32),
+
+    // Some(texture) of the layout size to draw to, which is stretched to
+    // fill the window, or None to draw to the window directly
+    frame: Option<Texture>,
+
+    // as loaded, and as changed in the game
+    loaded_settings: Settings,
+    settings: Settings,
+
     redraw: bool,
     last_redraw: u32,
+    title: String,
+    status: GameStatus,
+
+    // the animations started and not yet finished, and the board they
+    // were started from
+    anims: Vec<Playing>,
+    last_board: Option<Board>,
+
+    // an action was taken since the last board, which may have changed it
+    changed: bool,
+
+    // the next board comes from an undo or redo, which has a sound of its
+    // own and is not animated
+    from_history: bool,
 
-    // Some(new screen size) if need to relayout the widgets
-    resize: Option<(u32,u32)>
+    // Some(sounds) if built with the sound feature and there is an audio
+    // device
+    sounds: Option<Sounds>,
+
+    // relayout the widgets before the next draw
+    resize: bool
 }
 
 struct GuiState {
     selected_brush: Brush,
-    selected_value: u8
+    selected_value: u8,
+    selected_colour: u8,
+
+    // the kind of link drawn by the link brush
+    link_strong: bool,
+
+    // Some(candidate) at one end of a link, until the other end is picked
+    link_start: Option<(u8,u8,u8)>,
+
+    // Some((x, y)) once the keyboard has been used to move around
+    cursor: Option<(u8,u8)>,
+
+    // tiles for bulk actions
+    selection: Vec<(u8,u8)>,
+
+    // Some(tile) while the left button is held down on the board.  A
+    // click is applied when the button is released, unless it was
+    // dragged to another tile to select several, or to draw a link.
+    press: Option<(u8,u8)>,
+    dragging: bool,
+
+    // the candidate under the pointer when pressed, or 0
+    press_candidate: u8,
+
+    // Some(tile) under the mouse
+    hover: Option<(u8,u8)>,
+
+    // shade the tiles holding, or still able to hold, the selected value
+    highlight_value: bool,
+
+    // tint the row, column and block of the hovered tile and the cursor
+    highlight_peers: bool,
+
+    assisted_candidates: bool,
+
+    // Some(puzzle number typed so far) while jumping to a puzzle
+    goto_puzzle: Option<usize>,
+
+    show_stats: bool,
+    show_help: bool
 }
 
 struct Widget {
@@ -83,52 +172,117 @@ struct Widget {
     rect: Rect,
 }
 
+// how a tile is drawn besides its contents
+struct TileMarks {
+    wrong: bool,
+    cursor: bool,
+    selected: bool,
+
+    // holds the selected value
+    has_value: bool,
+
+    // the selected value is a remaining candidate
+    has_candidate: bool,
+
+    // in the row, column or block of the hovered tile or the cursor
+    peer: bool,
+
+    // darken the candidates that are the only place for their value
+    assisted: bool
+}
+
 impl<'a> Gui<'a> {
-    pub fn new() -> Gui<'a> {
+    pub fn new(settings: Settings) -> Gui<'a> {
         let sdl = sdl2::init().unwrap();
         let video = sdl.video().unwrap();
 
         init_png();
 
-        let screen_size = Gui::calc_screen_size_and_scale(
-                DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT);
-
-        let mut window
-            = video.window("Sudoku", DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT)
-            .resizable()
-            .position_centered()
-            .opengl()
-            .build().unwrap();
-
-        let _ = window.set_minimum_size(MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT);
+        let mut builder = video.window("Sudoku",
+                max(settings.window_width, 1),
+                max(settings.window_height, MIN_SCREEN_HEIGHT));
+        builder.resizable().position_centered().opengl().allow_highdpi();
+        if settings.fullscreen {
+            builder.fullscreen_desktop();
+        }
 
+        let window = builder.build().unwrap();
         let renderer = window.renderer().build().unwrap();
 
+        let theme = theme::by_name(&settings.theme).unwrap_or_else(|| {
+            eprintln!("settings: unknown theme: {}", settings.theme);
+            theme::light()
+        });
+
         let timer = sdl.timer().unwrap();
 
         let event_pump = sdl.event_pump().unwrap();
 
-        Gui {
-            gfx: GfxLib::new(renderer),
+        let resource_dirs = paths::resource_dirs(&settings.resource_path);
+        let font = load_font(settings.font.as_ref().map(|p| p.as_path()), &resource_dirs);
+        let sounds = Sounds::new(&sdl, &resource_dirs, settings.volume);
+
+        // the minimum size depends on the sprite sizes in the atlas
+        let mut gfx = GfxLib::new(renderer, &resource_dirs, theme);
+        let m = gfx.metrics;
+        let (min_w, min_h) = min_screen_size(&m);
+        let (window_size, (w, h)) = match gfx.renderer.window_mut() {
+            Some(window) => {
+                let _ = window.set_minimum_size(min_w, min_h);
+                let (window_w, window_h) = window.size();
+                if window_w < min_w || window_h < min_h {
+                    let _ = window.set_size(max(window_w, min_w), max(window_h, min_h));
+                }
+                (window.size(), window.drawable_size())
+            },
+            None => ((min_w, min_h), (min_w, min_h))
+        };
+        let screen_size = Gui::calc_screen_size_and_scale(&m, w, h);
+
+        let mut gui = Gui {
+            gfx: gfx,
             timer: timer,
             event_pump: event_pump,
-            state: GuiState::new(),
-            widgets: Gui::make_widgets(screen_size),
+            clipboard: video.clipboard(),
+            keyboard: sdl.keyboard(),
+            bindings: Bindings::load(),
+            state: GuiState::new(&settings),
+            widgets: Gui::make_widgets(&m, screen_size),
             screen_size: screen_size,
+            window_size: window_size,
+            frame: None,
+            loaded_settings: settings.clone(),
+            settings: settings,
             redraw: true,
             last_redraw: 0,
-            resize: None
-        }
+            title: String::from("Sudoku"),
+            status: GameStatus {
+                puzzle_position: None,
+                elapsed: 0,
+                paused: false,
+                completion: None,
+                mistakes: Vec::new(),
+                check_mode: false,
+                stats: None
+            },
+            anims: Vec::new(),
+            last_board: None,
+            changed: true,
+            from_history: false,
+            sounds: sounds,
+            resize: false
+        };
+
+        gui.gfx.font = font;
+        gui.relayout();
+        gui
     }
 
-    fn calc_screen_size_and_scale(screen_w: u32, screen_h: u32) -> ScreenSize {
-        let board_x_spacing = TILE_NUMBER_WIDTH + 4;
-        let board_y_spacing = TILE_NUMBER_HEIGHT + 4;
-        let board_w = board_x_spacing * 9 + 2;
-        let board_h = board_y_spacing * 9 + 2;
+    fn calc_screen_size_and_scale(m: &Metrics, screen_w: u32, screen_h: u32) -> ScreenSize {
+        let (board_w, board_h) = board_size(m);
 
-        let toolbar_w = MIN_TOOLBAR_WIDTH + TOOLBAR_BUTTON_WIDTH + 3; // sudoku
-        let toolbar_h = TOOLBAR_BUTTON_HEIGHT + 6;
+        let toolbar_w = min_toolbar_width(m) + m.label_w + 3; // sudoku
+        let toolbar_h = m.button_h + 6;
 
         let toolbar_x_scale = screen_w / toolbar_w;
         let toolbar_y_scale = (screen_h - board_h) / toolbar_h;
@@ -141,33 +295,99 @@ impl<'a> Gui<'a> {
         (screen_w, screen_h, board_scale, toolbar_scale)
     }
 
-    fn is_sudoku_label_visible(screen_size: ScreenSize) -> bool {
+    // The factor to stretch the layout for the given drawable size by, so
+    // that the board and toolbar fill the screen rather than leaving a
+    // margin up to a whole board scale wide.  The layout is then made for
+    // the drawable size divided by this.
+    fn calc_render_scale(m: &Metrics, pixel_w: u32, pixel_h: u32) -> f32 {
+        let (_, _, board_scale, toolbar_scale) =
+            Gui::calc_screen_size_and_scale(m, pixel_w, pixel_h);
+
+        // the smallest screen with room for the same layout
+        let (board_w, board_h) = board_size(m);
+        let used_w = max(board_scale * board_w, toolbar_scale * min_toolbar_width(m));
+        let used_h = max(board_scale * board_h + toolbar_scale * (m.button_h + 6),
+                MIN_SCREEN_HEIGHT);
+
+        let fit_x = pixel_w as f32 / used_w as f32;
+        let fit_y = pixel_h as f32 / used_h as f32;
+        fit_x.min(fit_y).max(1.0)
+    }
+
+    // Lays out the widgets for the drawable size of the window, which is
+    // larger than the window size on HiDPI displays.
+    fn relayout(&mut self) {
+        let (pixel_w, pixel_h) = match self.gfx.renderer.window() {
+            Some(window) => window.drawable_size(),
+            None => return
+        };
+
+        let m = self.gfx.metrics;
+        let render_scale =
+            if self.settings.smooth_scaling && self.gfx.renderer.render_target_supported() {
+                Gui::calc_render_scale(&m, pixel_w, pixel_h)
+            } else {
+                1.0
+            };
+        let (min_w, min_h) = min_screen_size(&m);
+        let w = max(min_w, (pixel_w as f32 / render_scale).ceil() as u32);
+        let h = max(min_h, (pixel_h as f32 / render_scale).ceil() as u32);
+
+        self.screen_size = Gui::calc_screen_size_and_scale(&m, w, h);
+        self.widgets = Gui::make_widgets(&m, self.screen_size);
+
+        // the sprites are scaled by whole numbers and stay sharp, and the
+        // frame is smoothed as it is stretched
+        self.frame = None;
+        if (w, h) != (pixel_w, pixel_h) {
+            hint::set("SDL_RENDER_SCALE_QUALITY", "linear");
+            match self.gfx.renderer.create_texture_target(PixelFormatEnum::ARGB8888, w, h) {
+                Ok(t) => self.frame = Some(t),
+                Err(e) => eprintln!("frame texture: {}", e)
+            }
+            hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+        }
+    }
+
+    // Converts a mouse position in window points to the layout.
+    fn to_screen_xy(&self, x: i32, y: i32) -> (i32, i32) {
+        let (screen_w, screen_h, _, _) = self.screen_size;
+        let (window_w, window_h) = self.window_size;
+
+        (x * screen_w as i32 / max(window_w, 1) as i32,
+         y * screen_h as i32 / max(window_h, 1) as i32)
+    }
+
+    fn is_sudoku_label_visible(m: &Metrics, screen_size: ScreenSize) -> bool {
         let (screen_w, _, _, toolbar_scale) = screen_size;
-        let toolbar_w = MIN_TOOLBAR_WIDTH + TOOLBAR_BUTTON_WIDTH + 3; // sudoku
+        let toolbar_w = min_toolbar_width(m) + m.label_w + 3; // sudoku
         screen_w >= toolbar_scale * toolbar_w
     }
 
-    fn make_widgets(screen_size: ScreenSize) -> Vec<Widget> {
+    fn make_widgets(m: &Metrics, screen_size: ScreenSize) -> Vec<Widget> {
         let mut ws = Vec::new();
         let (screen_w, screen_h, board_scale, toolbar_scale) = screen_size;
-        let y = (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 3)) as i32;
+        let y = (screen_h - toolbar_scale * (m.button_h + 3)) as i32;
 
-        let toolbar_spacing = TOOLBAR_NUMBER_WIDTH - 1;
-        let board_x_spacing = TILE_NUMBER_WIDTH + 4;
-        let board_y_spacing = TILE_NUMBER_HEIGHT + 4;
+        let toolbar_spacing = m.number_w - 1;
+        let board_x_spacing = m.tile_w + 4;
+        let board_y_spacing = m.tile_h + 4;
 
-        let label_visible = Gui::is_sudoku_label_visible(screen_size);
+        let label_visible = Gui::is_sudoku_label_visible(m, screen_size);
         let x_undo =
             if label_visible {
-                (toolbar_scale * (3 + TOOLBAR_BUTTON_WIDTH + 3)) as i32
+                (toolbar_scale * (3 + m.label_w + 3)) as i32
             } else {
                 (toolbar_scale * 3) as i32
             };
-        let x_redo = x_undo + (toolbar_scale * (TOOLBAR_UNDO_REDO_WIDTH + 2)) as i32;
+        let x_redo = x_undo + (toolbar_scale * (m.undo_redo_w + 2)) as i32;
+        let x_timer = x_redo + (toolbar_scale * (m.undo_redo_w + 4)) as i32;
         let x_1 = (screen_w - toolbar_scale * (toolbar_spacing * 9 + 4)) as i32;
-        let x_crossout = (x_redo + (toolbar_scale * (TOOLBAR_UNDO_REDO_WIDTH + 4)) as i32 + x_1) / 2;
-        let x_pencil = x_crossout - (toolbar_scale * (TOOLBAR_BUTTON_WIDTH + 2)) as i32;
-        let (board_x, board_y) = Gui::calc_board_xy(screen_size);
+        let x_palette = x_1 - (toolbar_scale * (TOOLBAR_PALETTE_WIDTH + 4)) as i32;
+        let x_crossout =
+            (x_timer + (toolbar_scale * (TOOLBAR_TIMER_WIDTH + 4)) as i32 + x_palette) / 2;
+        let x_pencil = x_crossout - (toolbar_scale * (m.button_w + 2)) as i32;
+        let (board_x, board_y) = Gui::calc_board_xy(m, screen_size);
 
         // label
         if label_visible {
@@ -176,8 +396,8 @@ impl<'a> Gui<'a> {
                     rect: Rect::new(
                             (toolbar_scale * 3) as i32,
                             y,
-                            toolbar_scale * TOOLBAR_BUTTON_WIDTH,
-                            toolbar_scale * TOOLBAR_BUTTON_HEIGHT)
+                            toolbar_scale * m.label_w,
+                            toolbar_scale * m.button_h)
                     });
         }
 
@@ -185,16 +405,24 @@ impl<'a> Gui<'a> {
         ws.push(Widget {
                 mode: WidgetType::Undo,
                 rect: Rect::new(x_undo, y,
-                        toolbar_scale * TOOLBAR_UNDO_REDO_WIDTH,
-                        toolbar_scale * TOOLBAR_BUTTON_HEIGHT),
+                        toolbar_scale * m.undo_redo_w,
+                        toolbar_scale * m.button_h),
                 });
 
         // redo
         ws.push(Widget {
                 mode: WidgetType::Redo,
                 rect: Rect::new(x_redo, y,
-                        toolbar_scale * TOOLBAR_UNDO_REDO_WIDTH,
-                        toolbar_scale * TOOLBAR_BUTTON_HEIGHT),
+                        toolbar_scale * m.undo_redo_w,
+                        toolbar_scale * m.button_h),
+                });
+
+        // timer
+        ws.push(Widget {
+                mode: WidgetType::Timer,
+                rect: Rect::new(x_timer, y,
+                        toolbar_scale * TOOLBAR_TIMER_WIDTH,
+                        toolbar_scale * m.button_h),
                 });
 
         // pencil
@@ -202,8 +430,8 @@ impl<'a> Gui<'a> {
                 mode: WidgetType::ToolbarBrush(
                         Brush::Pencil, Res::ToolbarActivePencil, Res::ToolbarInactivePencil),
                 rect: Rect::new(x_pencil, y,
-                        toolbar_scale * TOOLBAR_BUTTON_WIDTH,
-                        toolbar_scale * TOOLBAR_BUTTON_HEIGHT)
+                        toolbar_scale * m.button_w,
+                        toolbar_scale * m.button_h)
                 });
 
         // cross out
@@ -211,10 +439,23 @@ impl<'a> Gui<'a> {
                 mode: WidgetType::ToolbarBrush(
                         Brush::CrossOut, Res::ToolbarActiveCrossOut, Res::ToolbarInactiveCrossOut),
                 rect: Rect::new(x_crossout, y,
-                        toolbar_scale * TOOLBAR_BUTTON_WIDTH,
-                        toolbar_scale * TOOLBAR_BUTTON_HEIGHT)
+                        toolbar_scale * m.button_w,
+                        toolbar_scale * m.button_h)
                 });
 
+        // colours
+        for c in 1..NUM_COLOURS+1 {
+            let x = x_palette
+                + (toolbar_scale * (TOOLBAR_SWATCH_WIDTH + 1) * (c - 1) as u32) as i32;
+
+            ws.push(Widget {
+                    mode: WidgetType::ToolbarColour(c),
+                    rect: Rect::new(x, y,
+                            toolbar_scale * TOOLBAR_SWATCH_WIDTH,
+                            toolbar_scale * m.button_h)
+                    });
+        }
+
         // tiles
         for row in 0..9 {
             for col in 0..9 {
@@ -223,8 +464,8 @@ impl<'a> Gui<'a> {
                 ws.push(Widget {
                         mode: WidgetType::Tile(col as u8, row as u8),
                         rect: Rect::new(x, y,
-                                board_scale * TILE_NUMBER_WIDTH,
-                                board_scale * TILE_NUMBER_HEIGHT)
+                                board_scale * m.tile_w,
+                                board_scale * m.tile_h)
                         })
             }
         }
@@ -236,26 +477,25 @@ impl<'a> Gui<'a> {
             ws.push(Widget {
                     mode: WidgetType::ToolbarNumber(v),
                     rect: Rect::new(x, y,
-                            toolbar_scale * TOOLBAR_NUMBER_WIDTH,
-                            toolbar_scale * TOOLBAR_BUTTON_HEIGHT)
+                            toolbar_scale * m.number_w,
+                            toolbar_scale * m.button_h)
                     });
         }
 
         ws
     }
 
-    fn calc_board_xy(screen_size: ScreenSize) -> (i32, i32) {
+    fn calc_board_xy(m: &Metrics, screen_size: ScreenSize) -> (i32, i32) {
         let (screen_w, screen_h, board_scale, toolbar_scale) = screen_size;
-        let board_x_spacing = TILE_NUMBER_WIDTH + 4;
-        let board_y_spacing = TILE_NUMBER_HEIGHT + 4;
-        let x0 = (screen_w - board_scale * (board_x_spacing * 9 + 2)) / 2;
+        let (board_w, board_h) = board_size(m);
+        let x0 = (screen_w - board_scale * board_w) / 2;
         let y0 = (screen_h
-                    - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)
-                    - board_scale * (board_y_spacing * 9 + 2)) / 2;
+                    - toolbar_scale * (m.button_h + 6)
+                    - board_scale * board_h) / 2;
         (x0 as i32, y0 as i32)
     }
 
-    pub fn read_input(&mut self) -> SudokuAction {
+    fn read_event(&mut self) -> SudokuAction {
         let curr_ticks = self.timer.ticks();
         if curr_ticks >= self.last_redraw + 1000 / 60 {
             self.redraw = true;
@@ -268,42 +508,98 @@ impl<'a> Gui<'a> {
                 Event::Quit {..} =>
                     return SudokuAction::Quit,
 
-                Event::Window { win_event: WindowEvent::Resized(data1, data2), .. } =>
-                    self.resize = Some((data1 as u32, data2 as u32)),
+                Event::Window { win_event: WindowEvent::Resized(data1, data2), .. } => {
+                    self.resize = true;
+                    self.window_size = (data1 as u32, data2 as u32);
+                    if !self.is_fullscreen() {
+                        self.settings.window_width = data1 as u32;
+                        self.settings.window_height = data2 as u32;
+                    }
+                },
+
+                Event::Window { win_event: WindowEvent::Leave, .. } =>
+                    self.state.hover = None,
 
-                Event::KeyDown { keycode: Some(Keycode::F), .. }
-                | Event::KeyDown { keycode: Some(Keycode::F11), .. } => {
-                    self.toggle_fullscreen();
+                Event::Window { win_event: WindowEvent::FocusLost, .. }
+                | Event::Window { win_event: WindowEvent::Minimized, .. } =>
+                    return SudokuAction::Pause,
+
+                // any click or escape closes the help and stats screens
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. }
+                | Event::MouseButtonDown { mouse_btn: MouseButton::Left, .. }
+                if self.state.show_help || self.state.show_stats => {
+                    self.state.show_help = false;
+                    self.state.show_stats = false;
                     return SudokuAction::NoOp
                 },
 
-                Event::KeyDown { keycode: Some(k), .. } =>
-                    match self.state.on_key_down(k) {
-                        SudokuAction::NoOp => {},
-                        a => return a
+                // enter or escape dismisses the completion screen
+                Event::KeyDown { keycode: Some(Keycode::Return), .. }
+                | Event::KeyDown { keycode: Some(Keycode::KpEnter), .. }
+                | Event::KeyDown { keycode: Some(Keycode::Escape), .. }
+                if self.status.completion.is_some() =>
+                    return SudokuAction::Continue,
+
+                Event::KeyDown { keycode: Some(k), keymod, .. } =>
+                    if let Some(n) = self.state.goto_puzzle {
+                        return self.state.on_goto_puzzle_key_down(k, n);
+                    } else if let Some(c) = self.bindings.lookup(Input::Key(k), keymod) {
+                        return self.on_command(c);
                     },
 
-                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } =>
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    let (x, y) = self.to_screen_xy(x, y);
                     if let Some(w) = Gui::find_widget(&self.widgets, x, y) {
-                        match self.state.on_lmb(&w) {
+                        let keymod = self.keyboard.mod_state();
+                        self.state.press_candidate =
+                            candidate_at(w.rect, self.screen_size.2, x, y);
+                        match self.state.on_lmb(&w, keymod) {
                             SudokuAction::NoOp => return SudokuAction::NoOp,
                             a => return a
                         }
-                    },
+                    }
+                },
+
+                Event::MouseMotion { mousestate, x, y, .. } => {
+                    let (x, y) = self.to_screen_xy(x, y);
+                    if let Some(&WidgetType::Tile(tx,ty)) =
+                            Gui::find_widget(&self.widgets, x, y).map(|w| &w.mode) {
+                        self.state.hover = Some((tx, ty));
+                        if mousestate.left() {
+                            self.state.on_drag(tx, ty);
+                        }
+                    } else {
+                        self.state.hover = None;
+                    }
+                },
+
+                Event::MouseButtonUp { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    let (x, y) = self.to_screen_xy(x, y);
+                    let release = match Gui::find_widget(&self.widgets, x, y) {
+                        Some(&Widget { mode: WidgetType::Tile(tx,ty), rect }) =>
+                            Some((tx, ty, candidate_at(rect, self.screen_size.2, x, y))),
+                        _ => None
+                    };
+                    return self.state.on_lmb_up(release);
+                },
 
-                Event::MouseButtonDown { mouse_btn: MouseButton::Right, x, y, .. } =>
+                Event::MouseButtonDown { mouse_btn: MouseButton::Right, x, y, .. } => {
+                    let (x, y) = self.to_screen_xy(x, y);
                     if let Some(w) = Gui::find_widget(&self.widgets, x, y) {
                         match self.state.on_rmb(&w) {
                             SudokuAction::NoOp => {},
                             a => return a
                         }
-                    },
-
-                Event::MouseButtonDown { mouse_btn: MouseButton::X1, .. } =>
-                    return SudokuAction::Undo,
+                    }
+                },
 
-                Event::MouseButtonDown { mouse_btn: MouseButton::X2, .. } =>
-                    return SudokuAction::Redo,
+                // the other buttons are bound like keys
+                Event::MouseButtonDown { mouse_btn, .. } => {
+                    let keymod = self.keyboard.mod_state();
+                    if let Some(c) = self.bindings.lookup(Input::Mouse(mouse_btn), keymod) {
+                        return self.on_command(c);
+                    }
+                },
 
                 Event::MouseWheel { y, .. } =>
                     self.state.on_wheel(y),
@@ -318,6 +614,30 @@ impl<'a> Gui<'a> {
         SudokuAction::NoOp
     }
 
+    // Commands that need more than the GuiState.
+    fn on_command(&mut self, command: Command) -> SudokuAction {
+        match command {
+            Command::Fullscreen => self.toggle_fullscreen(),
+
+            Command::Theme => {
+                self.gfx.theme = theme::next(self.gfx.theme.name);
+                self.settings.theme = String::from(self.gfx.theme.name);
+            },
+
+            Command::Mute => self.settings.mute = !self.settings.mute,
+
+            Command::Paste =>
+                match self.clipboard.clipboard_text() {
+                    Ok(contents) => return SudokuAction::NewFromString(contents),
+                    Err(e) => eprintln!("clipboard: {}", e)
+                },
+
+            c => return self.state.on_command(c)
+        }
+
+        SudokuAction::NoOp
+    }
+
     fn find_widget(widgets: &Vec<Widget>, x: i32, y: i32) -> Option<&Widget> {
         widgets.iter().find(|w| {
                 let r = &w.rect;
@@ -326,42 +646,141 @@ impl<'a> Gui<'a> {
     }
 
     fn toggle_fullscreen(&mut self) {
-        let mut window = self.gfx.renderer.window_mut().unwrap();
+        let fullscreen = {
+            let window = self.gfx.renderer.window_mut().unwrap();
 
-        if window.window_flags() & SDL_WINDOW_FULLSCREEN_DESKTOP != 0 {
-            window.set_fullscreen(FullscreenType::Off).unwrap();
-        } else {
-            window.set_fullscreen(FullscreenType::Desktop).unwrap();
+            if window.window_flags() & SDL_WINDOW_FULLSCREEN_DESKTOP != 0 {
+                window.set_fullscreen(FullscreenType::Off).unwrap();
+                false
+            } else {
+                window.set_fullscreen(FullscreenType::Desktop).unwrap();
+                true
+            }
+        };
+
+        self.settings.fullscreen = fullscreen;
+    }
+
+    fn is_fullscreen(&self) -> bool {
+        self.gfx.renderer.window().map_or(false,
+                |w| w.window_flags() & SDL_WINDOW_FULLSCREEN_DESKTOP != 0)
+    }
+
+    // Writes back the settings changed in the game, if any.
+    fn save_settings(&mut self) {
+        self.settings.brush = self.state.selected_brush;
+        self.settings.highlight_value = self.state.highlight_value;
+        self.settings.highlight_peers = self.state.highlight_peers;
+
+        if self.settings != self.loaded_settings {
+            match self.settings.save() {
+                Ok(()) => self.loaded_settings = self.settings.clone(),
+                Err(e) => eprintln!("settings: {}", e)
+            }
         }
     }
 
-    pub fn draw_to_screen(&mut self, board: &Board) {
+    fn update_title(&mut self) {
+        let mut title = String::from("Sudoku");
+
+        if let Some((n, count)) = self.status.puzzle_position {
+            if count > 1 {
+                title.push_str(&format!(" - {} / {}", n + 1, count));
+            }
+        }
+
+        if let Some(c) = self.status.completion {
+            title.push_str(&format!(" - Solved in {}", format_ms(c.elapsed)));
+        } else if self.status.check_mode {
+            title.push_str(" - Checking");
+        }
+
+        if self.state.selected_brush == Brush::Link {
+            title.push_str(if self.state.link_strong { " - Strong links" } else { " - Weak links" });
+        }
+
+        match self.state.goto_puzzle {
+            Some(0) => title.push_str(" - Go to puzzle: _"),
+            Some(n) => title.push_str(&format!(" - Go to puzzle: {}_", n)),
+            None => {}
+        }
+
+        if title != self.title {
+            if let Some(window) = self.gfx.renderer.window_mut() {
+                let _ = window.set_title(&title);
+            }
+            self.title = title;
+        }
+    }
+
+    // Starts the animations and sound for what changed since the last
+    // board, if an action was taken.
+    fn start_feedback(&mut self, board: &Board) {
+        if !self.changed {
+            return;
+        }
+
+        if let Some(ref old) = self.last_board {
+            let changes = board.changes_from(old);
+
+            if !self.from_history {
+                if self.settings.animations {
+                    self.anims.extend(anim::from_changes(&changes, self.timer.ticks()));
+                }
+
+                if let Some(s) = sound::from_changes(&changes) {
+                    self.play(s);
+                }
+            }
+        }
+
+        self.changed = false;
+        self.from_history = false;
+        self.last_board = Some(board.clone());
+    }
+
+    fn play(&mut self, sound: Sound) {
+        if let (Some(ref mut sounds), false) = (self.sounds.as_mut(), self.settings.mute) {
+            sounds.play(sound);
+        }
+    }
+
+    fn draw_to_screen(&mut self, board: &Board) {
         if !self.redraw {
             return;
         }
 
-        if let Some((new_w, new_h)) = self.resize {
-            self.screen_size = Gui::calc_screen_size_and_scale(new_w, new_h);
-            self.widgets = Gui::make_widgets(self.screen_size);
-            self.resize = None;
+        self.update_title();
+
+        if self.resize {
+            self.relayout();
+            self.resize = false;
+        }
+
+        // draw to the frame, if stretching it
+        if let Some(frame) = self.frame.take() {
+            let res = self.gfx.renderer.render_target().map(|mut rt| rt.set(frame));
+            if let Some(Err(e)) = res {
+                eprintln!("frame texture: {}", e);
+                self.resize = true;
+            }
         }
 
         let (screen_w, screen_h, board_scale, toolbar_scale) = self.screen_size;
-        let colour_white = Color::RGB(0xD0, 0xD0, 0xD0);
-        let colour_light_grey = Color::RGB(0x98, 0x98, 0x98);
-        let colour_dark_grey = Color::RGB(0x58, 0x58, 0x58);
+        let theme = self.gfx.theme.clone();
+        let toolbar_h = self.gfx.metrics.button_h + 6;
 
         let toolbar_rect = Rect::new(
                 0,
-                (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32,
+                (screen_h - toolbar_scale * toolbar_h) as i32,
                 screen_w,
-                toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6));
+                toolbar_scale * toolbar_h);
 
-        self.gfx.renderer.set_draw_color(colour_white);
+        self.gfx.renderer.set_draw_color(theme.background);
         self.gfx.renderer.clear();
 
         // board
-        self.gfx.renderer.set_draw_color(colour_light_grey);
+        self.gfx.renderer.set_draw_color(theme.grid);
         for &y in [1,2,4,5,7,8].iter() {
             Gui::draw_board_hline(&mut self.gfx, self.screen_size, y);
         }
@@ -369,7 +788,7 @@ impl<'a> Gui<'a> {
             Gui::draw_board_vline(&mut self.gfx, self.screen_size, x);
         }
 
-        self.gfx.renderer.set_draw_color(colour_dark_grey);
+        self.gfx.renderer.set_draw_color(theme.grid_block);
         for &y in [0,3,6,9].iter() {
             Gui::draw_board_hline(&mut self.gfx, self.screen_size, y);
         }
@@ -378,15 +797,39 @@ impl<'a> Gui<'a> {
         }
 
         // toolbar
-        self.gfx.renderer.set_draw_color(colour_light_grey);
+        self.gfx.renderer.set_draw_color(theme.toolbar);
         let _ = self.gfx.renderer.fill_rect(toolbar_rect);
 
-        self.gfx.renderer.set_draw_color(colour_dark_grey);
+        self.gfx.renderer.set_draw_color(theme.toolbar_border);
         let _ = self.gfx.renderer.draw_rect(toolbar_rect);
 
         // widgets
         for w in self.widgets.iter() {
-            Gui::draw_widget(&mut self.gfx, board_scale, w, board, &self.state);
+            Gui::draw_widget(&mut self.gfx, board_scale, w, board, &self.state, &self.status);
+        }
+        Gui::draw_position(&mut self.gfx, &self.widgets, &self.state, &self.status);
+
+        if !self.status.paused {
+            Gui::draw_links(&mut self.gfx, board_scale, &self.widgets, board, &self.state);
+            Gui::draw_anims(&mut self.gfx, self.screen_size, &self.widgets,
+                    &mut self.anims, self.timer.ticks());
+        }
+
+        if self.state.show_help {
+            Gui::draw_help(&mut self.gfx, self.screen_size, &self.bindings);
+        } else if self.state.show_stats {
+            let s = self.status.stats.clone().unwrap_or(PuzzleStats::new());
+            Gui::draw_stats(&mut self.gfx, self.screen_size, &s);
+        } else if let Some(c) = self.status.completion {
+            let best = self.status.stats.as_ref().and_then(|s| s.best);
+            Gui::draw_completion(&mut self.gfx, self.screen_size, &c, best);
+        }
+
+        // stretch the frame to the window
+        if let Some(Ok(Some(frame))) =
+                self.gfx.renderer.render_target().map(|mut rt| rt.reset()) {
+            let _ = self.gfx.renderer.copy(&frame, None, None);
+            self.frame = Some(frame);
         }
 
         self.gfx.renderer.present();
@@ -396,9 +839,10 @@ impl<'a> Gui<'a> {
 
     fn draw_board_hline(gfx: &mut GfxLib, screen_size: ScreenSize, y: u32) {
         let (_, _, scale, _) = screen_size;
-        let board_x_spacing = TILE_NUMBER_WIDTH + 4;
-        let board_y_spacing = TILE_NUMBER_HEIGHT + 4;
-        let (board_x, board_y) = Gui::calc_board_xy(screen_size);
+        let m = gfx.metrics;
+        let board_x_spacing = m.tile_w + 4;
+        let board_y_spacing = m.tile_h + 4;
+        let (board_x, board_y) = Gui::calc_board_xy(&m, screen_size);
 
         let hline = Rect::new(
                 board_x,
@@ -411,9 +855,10 @@ impl<'a> Gui<'a> {
 
     fn draw_board_vline(gfx: &mut GfxLib, screen_size: ScreenSize, x: u32) {
         let (_, _, scale, _) = screen_size;
-        let board_x_spacing = TILE_NUMBER_WIDTH + 4;
-        let board_y_spacing = TILE_NUMBER_HEIGHT + 4;
-        let (board_x, board_y) = Gui::calc_board_xy(screen_size);
+        let m = gfx.metrics;
+        let board_x_spacing = m.tile_w + 4;
+        let board_y_spacing = m.tile_h + 4;
+        let (board_x, board_y) = Gui::calc_board_xy(&m, screen_size);
 
         let vline = Rect::new(
                 board_x + (scale * board_x_spacing * x) as i32,
@@ -425,15 +870,36 @@ impl<'a> Gui<'a> {
     }
 
     fn draw_widget(gfx: &mut GfxLib, scale: u32,
-            widget: &Widget, board: &Board, state: &GuiState) {
+            widget: &Widget, board: &Board, state: &GuiState, status: &GameStatus) {
         let res = match widget.mode {
             WidgetType::Label => Res::ToolbarSudoku,
             WidgetType::Undo => Res::ToolbarUndo,
             WidgetType::Redo => Res::ToolbarRedo,
 
+            WidgetType::Timer => {
+                Gui::draw_timer(gfx, status, widget.rect);
+                return;
+            },
+
             WidgetType::Tile(x,y) => {
-                if let Some(t) = board.get(x,y) {
-                    Gui::draw_tile(gfx, scale, board, t, widget.rect);
+                // hide the board while paused
+                if let (Some(t), false) = (board.get(x,y), status.paused) {
+                    let v = state.selected_value;
+                    let is_peer = |focus: Option<(u8,u8)>| focus.map_or(false,
+                            |(fx, fy)| fx == x || fy == y || t.is_in_same_block(fx, fy));
+
+                    let marks = TileMarks {
+                        wrong: status.mistakes.iter().any(|&m| m == (x,y)),
+                        cursor: state.cursor == Some((x,y)),
+                        selected: state.selection.iter().any(|&s| s == (x,y)),
+                        has_value: state.highlight_value && t.assignment == Some(v),
+                        has_candidate: state.highlight_value && t.assignment.is_none()
+                            && t.candidates.contains(&v) && !t.eliminated.contains(&v),
+                        peer: state.highlight_peers
+                            && (is_peer(state.hover) || is_peer(state.cursor)),
+                        assisted: state.assisted_candidates
+                    };
+                    Gui::draw_tile(gfx, scale, board, t, &marks, widget.rect);
                 }
                 return;
             },
@@ -450,18 +916,390 @@ impl<'a> Gui<'a> {
                     Res::ToolbarActiveNumber(v)
                 } else {
                     Res::ToolbarInactiveNumber(v)
-                }
+                },
+
+            WidgetType::ToolbarColour(c) => {
+                let active = state.selected_brush == Brush::Colour && state.selected_colour == c;
+                Gui::draw_swatch(gfx, c, active, widget.rect);
+                return;
+            }
         };
 
         gfx.draw(res, widget.rect);
     }
 
+    // The number of the puzzle in the collection, or the number being
+    // typed to go to, between the timer and the brushes if there is room.
+    // The title shows it too, but not in fullscreen.
+    fn draw_position(gfx: &mut GfxLib, widgets: &Vec<Widget>,
+            state: &GuiState, status: &GameStatus) {
+        let text = match (state.goto_puzzle, status.puzzle_position) {
+            (Some(0), _) => String::from("go to _"),
+            (Some(n), _) => format!("go to {}_", n),
+            (None, Some((n, count))) if count > 1 => format!("{}/{}", n + 1, count),
+            _ => return
+        };
+
+        let timer = widgets.iter().find(|w| match w.mode {
+                WidgetType::Timer => true,
+                _ => false
+            });
+        let brush = widgets.iter().find(|w| match w.mode {
+                WidgetType::ToolbarBrush(..) => true,
+                _ => false
+            });
+
+        if let (Some(timer), Some(brush)) = (timer, brush) {
+            let scale = timer.rect.height() / gfx.metrics.button_h;
+            let text_w = scale * (GLYPH_WIDTH + 1) * text.len() as u32;
+            let x = timer.rect.right() + (scale * 4) as i32;
+            if x + text_w as i32 > brush.rect.x() - (scale * 2) as i32 {
+                return;
+            }
+
+            let y = timer.rect.y() + ((timer.rect.height() - scale * GLYPH_HEIGHT) / 2) as i32;
+            let colour = gfx.theme.text;
+            gfx.renderer.set_draw_color(colour);
+            Gui::draw_text(gfx, &text, x, y, scale);
+        }
+    }
+
+    // Links are drawn between the candidate dots, solid if strong and
+    // dashed if weak.  Links to candidates no longer shown are hidden.
+    fn draw_links(gfx: &mut GfxLib, scale: u32,
+            widgets: &Vec<Widget>, board: &Board, state: &GuiState) {
+        let tile_rect = |x: u8, y: u8| widgets.iter().find(|w| match w.mode {
+                WidgetType::Tile(wx, wy) => (wx, wy) == (x, y),
+                _ => false
+            }).map(|w| w.rect);
+
+        let colour = gfx.theme.link;
+        gfx.renderer.set_draw_color(colour);
+
+        for &Link { from, to, strong } in board.links().iter() {
+            let (x1, y1, v1) = from;
+            let (x2, y2, v2) = to;
+            if !board.is_shown_candidate(x1, y1, v1) || !board.is_shown_candidate(x2, y2, v2) {
+                continue;
+            }
+
+            if let (Some(r1), Some(r2)) = (tile_rect(x1, y1), tile_rect(x2, y2)) {
+                Gui::draw_link(gfx, candidate_xy(r1, scale, v1), candidate_xy(r2, scale, v2),
+                        strong, scale);
+            }
+        }
+
+        // a box around the first end of a link being drawn
+        if let Some((x, y, v)) = state.link_start {
+            if let Some(r) = tile_rect(x, y) {
+                let (cx, cy) = candidate_xy(r, scale, v);
+                let _ = gfx.renderer.draw_rect(Rect::new(
+                        cx - (scale * 3 / 2) as i32,
+                        cy - (scale * 3 / 2) as i32,
+                        scale * 3,
+                        scale * 3));
+            }
+        }
+    }
+
+    // Draws the frame of each animation due now, and forgets those that
+    // have finished.
+    fn draw_anims(gfx: &mut GfxLib, screen_size: ScreenSize,
+            widgets: &Vec<Widget>, anims: &mut Vec<Playing>, now: u32) {
+        let (_, _, board_scale, _) = screen_size;
+        let m = gfx.metrics;
+
+        anims.retain(|a| gfx.anim_length(a.kind).map_or(false,
+                |(frames, frame_ms)| now < a.start + frames * frame_ms));
+
+        for a in anims.iter().filter(|a| a.start <= now) {
+            let dst = match a.target {
+                Target::Tile(x, y) => widgets.iter().find(|w| match w.mode {
+                        WidgetType::Tile(wx, wy) => (wx, wy) == (x, y),
+                        _ => false
+                    }).map(|w| w.rect),
+
+                Target::Board => {
+                    let (board_x, board_y) = Gui::calc_board_xy(&m, screen_size);
+                    let (board_w, board_h) = board_size(&m);
+                    Some(Rect::new(board_x, board_y,
+                            board_scale * board_w, board_scale * board_h))
+                }
+            };
+
+            if let (Some(dst), Some((_, frame_ms))) = (dst, gfx.anim_length(a.kind)) {
+                gfx.draw_anim(a.kind, (now - a.start) / frame_ms, dst, board_scale);
+            }
+        }
+    }
+
+    // A line scale pixels wide, from dots of scale x scale pixels.  Dashes
+    // are three dots long.
+    fn draw_link(gfx: &mut GfxLib,
+            (x1, y1): (i32, i32), (x2, y2): (i32, i32), strong: bool, scale: u32) {
+        let n = max((x2 - x1).abs(), (y2 - y1).abs());
+        let dash = max(1, 3 * scale as i32);
+
+        for i in 0..n+1 {
+            if !strong && (i / dash) % 2 == 1 {
+                continue;
+            }
+
+            let x = x1 + (x2 - x1) * i / max(n, 1);
+            let y = y1 + (y2 - y1) * i / max(n, 1);
+            let _ = gfx.renderer.fill_rect(Rect::new(
+                    x - (scale / 2) as i32, y - (scale / 2) as i32, scale, scale));
+        }
+    }
+
+    // The active swatch is outlined, the others are drawn smaller.
+    fn draw_swatch(gfx: &mut GfxLib, colour: u8, active: bool, dst: Rect) {
+        let scale = dst.height() / gfx.metrics.button_h;
+
+        if active {
+            let colour = gfx.theme.cursor;
+            gfx.renderer.set_draw_color(colour);
+            let _ = gfx.renderer.fill_rect(dst);
+        }
+
+        let colour = palette_colour(&gfx.theme, colour);
+        gfx.renderer.set_draw_color(colour);
+        let _ = gfx.renderer.fill_rect(Rect::new(
+                dst.x() + scale as i32,
+                dst.y() + scale as i32,
+                dst.width() - scale * 2,
+                dst.height() - scale * 2));
+    }
+
+    fn draw_timer(gfx: &mut GfxLib, status: &GameStatus, dst: Rect) {
+        let scale = dst.height() / gfx.metrics.button_h;
+        let colour =
+            if status.paused {
+                gfx.theme.timer_paused
+            } else {
+                gfx.theme.text
+            };
+
+        gfx.renderer.set_draw_color(colour);
+        Gui::draw_segments(gfx, &format_ms(status.elapsed),
+                dst.x(), dst.y() + scale as i32, scale);
+    }
+
+    // The solve time, undos, auto-fills and best time.
+    fn draw_completion(gfx: &mut GfxLib, screen_size: ScreenSize,
+            completion: &Completion, best: Option<u64>) {
+        let mut lines = vec![
+            format!("solved in {}", format_ms(completion.elapsed)),
+            format!("undos {}", completion.undos),
+            format!("auto-fills {}", completion.autofills)];
+
+        if let Some(ms) = best {
+            lines.push(format!("best {}", format_ms(ms)));
+        }
+
+        Gui::draw_panel(gfx, screen_size, &lines);
+    }
+
+    fn draw_stats(gfx: &mut GfxLib, screen_size: ScreenSize, stats: &PuzzleStats) {
+        let last_played = if stats.last_played.is_empty() { "-" } else { &stats.last_played };
+        let lines = vec![
+            format!("attempts {}", stats.attempts),
+            format!("completions {}", stats.completions),
+            format!("best {}", stats.best.map_or(String::from("-"), format_ms)),
+            format!("hints {}", stats.hints),
+            format!("last played {}", last_played)];
+
+        Gui::draw_panel(gfx, screen_size, &lines);
+    }
+
+    // Lines of text in a panel over the board, twice the board scale if
+    // they fit.
+    fn draw_panel(gfx: &mut GfxLib, screen_size: ScreenSize, lines: &Vec<String>) {
+        let (_, _, board_scale, _) = screen_size;
+        let m = gfx.metrics;
+        let (board_w, board_h) = board_size(&m);
+        let (board_w, board_h) = (board_scale * board_w, board_scale * board_h);
+        let (board_x, board_y) = Gui::calc_board_xy(&m, screen_size);
+
+        let char_w = GLYPH_WIDTH + 1;
+        let line_h = GLYPH_HEIGHT + 3;
+        let text_w = char_w * lines.iter().map(|l| l.len() as u32).max().unwrap_or(0);
+        let scale = max(1, min(board_scale * 2, board_w / (text_w + 5)));
+        let panel_w = scale * (text_w + 5);
+        let panel_h = scale * (line_h * lines.len() as u32 + 3);
+        let panel = Rect::new(
+                board_x + ((board_w - min(panel_w, board_w)) / 2) as i32,
+                board_y + ((board_h - min(panel_h, board_h)) / 2) as i32,
+                panel_w, panel_h);
+
+        let (text, background) = (gfx.theme.text, gfx.theme.panel);
+        gfx.renderer.set_draw_color(text);
+        let _ = gfx.renderer.fill_rect(panel);
+        gfx.renderer.set_draw_color(background);
+        let _ = gfx.renderer.fill_rect(Rect::new(
+                panel.x() + board_scale as i32,
+                panel.y() + board_scale as i32,
+                panel_w - board_scale * 2,
+                panel_h - board_scale * 2));
+
+        gfx.renderer.set_draw_color(text);
+        for (i, line) in lines.iter().enumerate() {
+            let x = panel.x() + (scale * (text_w + 6 - char_w * line.len() as u32) / 2) as i32;
+            let y = panel.y() + (scale * (line_h * i as u32 + 3)) as i32;
+            Gui::draw_text(gfx, line, x, y, scale);
+        }
+    }
+
+    // The bindings of every command, in as many columns as needed to fit
+    // the window, as large as will fit.
+    fn draw_help(gfx: &mut GfxLib, screen_size: ScreenSize, bindings: &Bindings) {
+        let (screen_w, screen_h, board_scale, _) = screen_size;
+        let lines: Vec<String> = bindings.describe().iter()
+            .map(|&(ref command, ref inputs)| format!("{:<16}{}", command, inputs.join(", ")))
+            .collect();
+
+        let char_w = GLYPH_WIDTH + 1;
+        let line_h = GLYPH_HEIGHT + 2;
+        let col_w = char_w * (lines.iter().map(|l| l.len() as u32).max().unwrap_or(0) + 2);
+        let rows = |cols: u32| (lines.len() as u32 + cols - 1) / cols;
+
+        let mut layout = (1, 4);
+        'fit: for scale in (1..board_scale+1).rev() {
+            for cols in 1..5 {
+                if scale * (col_w * cols + 4) <= screen_w
+                        && scale * (line_h * rows(cols) + 4) <= screen_h {
+                    layout = (scale, cols);
+                    break 'fit;
+                }
+            }
+        }
+
+        let (scale, cols) = layout;
+        let panel_w = scale * (col_w * cols + 4);
+        let panel_h = scale * (line_h * rows(cols) + 4);
+        let panel = Rect::new(
+                ((screen_w - min(panel_w, screen_w)) / 2) as i32,
+                ((screen_h - min(panel_h, screen_h)) / 2) as i32,
+                panel_w, panel_h);
+
+        let (text, background) = (gfx.theme.text, gfx.theme.panel);
+        gfx.renderer.set_draw_color(text);
+        let _ = gfx.renderer.fill_rect(panel);
+        gfx.renderer.set_draw_color(background);
+        let _ = gfx.renderer.fill_rect(Rect::new(
+                panel.x() + scale as i32,
+                panel.y() + scale as i32,
+                panel_w - scale * 2,
+                panel_h - scale * 2));
+
+        gfx.renderer.set_draw_color(text);
+        for (i, line) in lines.iter().enumerate() {
+            let col = i as u32 / rows(cols);
+            let row = i as u32 % rows(cols);
+            Gui::draw_text(gfx, line,
+                    panel.x() + (scale * (col_w * col + 2 + char_w)) as i32,
+                    panel.y() + (scale * (line_h * row + 3)) as i32,
+                    scale);
+        }
+    }
+
+    // Draws text in the pixel font, or in the same cells in the font if
+    // there is one.
+    fn draw_text(gfx: &mut GfxLib, text: &str, x0: i32, y0: i32, scale: u32) {
+        for (i, c) in text.chars().enumerate() {
+            let x = x0 + (scale * (GLYPH_WIDTH + 1) * i as u32) as i32;
+
+            if let Some(ref mut font) = gfx.font {
+                let colour = gfx.renderer.draw_color();
+                let centre = (x + (scale * GLYPH_WIDTH / 2) as i32,
+                        y0 + (scale * GLYPH_HEIGHT / 2) as i32);
+                font.draw_char(&mut gfx.renderer, c, centre, scale * (GLYPH_HEIGHT + 2), colour);
+                continue;
+            }
+
+            for (gy, &bits) in glyph(c).iter().enumerate() {
+                for gx in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - gx)) != 0 {
+                        let _ = gfx.renderer.fill_rect(Rect::new(
+                                x + (scale * gx) as i32,
+                                y0 + (scale * gy as u32) as i32,
+                                scale, scale));
+                    }
+                }
+            }
+        }
+    }
+
+    // Draws digits and colons as seven-segment glyphs, or in the same cells
+    // in the font if there is one.
+    fn draw_segments(gfx: &mut GfxLib, text: &str, x0: i32, y0: i32, scale: u32) {
+        // segments a-g as (x, y, w, h)
+        let segments = [
+            (0, 0, 3, 1), (2, 0, 1, 4), (2, 3, 1, 4), (0, 6, 3, 1),
+            (0, 3, 1, 4), (0, 0, 1, 4), (0, 3, 3, 1)];
+        let digits = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+        let mut x = x0;
+
+        for c in text.chars() {
+            if let Some(ref mut font) = gfx.font {
+                let w = if c == ':' { SEGMENT_COLON_WIDTH } else { SEGMENT_DIGIT_WIDTH };
+                let colour = gfx.renderer.draw_color();
+                let centre = (x + (scale * (w - 1) / 2) as i32, y0 + (scale * 7 / 2) as i32);
+                font.draw_char(&mut gfx.renderer, c, centre, scale * 9, colour);
+                x = x + (scale * w) as i32;
+                continue;
+            }
+
+            if c == ':' {
+                for &sy in [2, 4].iter() {
+                    let _ = gfx.renderer.fill_rect(Rect::new(
+                            x, y0 + (scale * sy) as i32, scale, scale));
+                }
+                x = x + (scale * SEGMENT_COLON_WIDTH) as i32;
+                continue;
+            }
+
+            let glyph = c.to_digit(10).map_or(0, |d| digits[d as usize]);
+            for (i, &(sx, sy, sw, sh)) in segments.iter().enumerate() {
+                if glyph & (1 << i) != 0 {
+                    let _ = gfx.renderer.fill_rect(Rect::new(
+                            x + (scale * sx) as i32,
+                            y0 + (scale * sy) as i32,
+                            scale * sw,
+                            scale * sh));
+                }
+            }
+            x = x + (scale * SEGMENT_DIGIT_WIDTH) as i32;
+        }
+    }
+
     fn draw_tile(gfx: &mut GfxLib, scale: u32,
-            board: &Board, tile: &Tile, dst: Rect) {
-        // chequer pattern
-        if (tile.x + tile.y) % 2 != 0 {
-            let colour_rose = Color::RGB(0xC2, 0xBC, 0xBC);
-            gfx.renderer.set_draw_color(colour_rose);
+            board: &Board, tile: &Tile, marks: &TileMarks, dst: Rect) {
+        let theme = gfx.theme.clone();
+
+        // red for a mistake, blue if selected, green for the selected
+        // value, yellow for peers, otherwise chequer pattern
+        let background =
+            if marks.wrong {
+                Some(theme.wrong)
+            } else if marks.selected {
+                Some(theme.selected)
+            } else if board.tile_colour(tile.x, tile.y) != 0 {
+                Some(palette_colour(&theme, board.tile_colour(tile.x, tile.y)))
+            } else if marks.has_value {
+                Some(theme.has_value)
+            } else if marks.has_candidate {
+                Some(theme.has_candidate)
+            } else if marks.peer {
+                Some(theme.peer)
+            } else if (tile.x + tile.y) % 2 != 0 {
+                Some(theme.chequer)
+            } else {
+                None
+            };
+
+        if let Some(colour) = background {
+            gfx.renderer.set_draw_color(colour);
             let _ = gfx.renderer.fill_rect(Rect::new(
                     dst.x() - (scale * 1) as i32,
                     dst.y() - (scale * 1) as i32,
@@ -479,13 +1317,22 @@ impl<'a> Gui<'a> {
                     Res::TileConflict(v)
                 };
             gfx.draw(res, dst);
+
+            // underline conflicts, for themes that do not rely on colour
+            if theme.mark_conflicts && !tile.is_init() && !tile.is_guess() {
+                let (_, _, colour) = theme.numbers;
+                gfx.renderer.set_draw_color(colour);
+                let _ = gfx.renderer.fill_rect(Rect::new(
+                        dst.x() + (scale * 3) as i32,
+                        dst.y() + (dst.height() - scale) as i32,
+                        dst.width() - scale * 6,
+                        scale));
+            }
         } else {
             let x_spacing: u32 = 3;
             let y_spacing: u32 = 3;
             let x0 = dst.x() + ((dst.width() - scale * 1) / 2 - scale * x_spacing) as i32;
             let y0 = dst.y() + ((dst.height() - scale * 1) / 2 - scale * y_spacing) as i32;
-            let colour_light_grey = Color::RGB(0x98, 0x98, 0x98);
-            let colour_dark_grey = Color::RGB(0x58, 0x58, 0x58);
 
             for &v in tile.candidates.iter().filter(
                     |&&v1| tile.eliminated.iter().all(|&v2| v1 != v2)) {
@@ -493,12 +1340,31 @@ impl<'a> Gui<'a> {
                     let x = (v - 1) % 3;
                     let y = 2 - (v - 1) / 3;
 
-                    if board.is_unique(tile, v) {
-                        gfx.renderer.set_draw_color(colour_dark_grey);
-                    } else {
-                        gfx.renderer.set_draw_color(colour_light_grey);
+                    let c = board.candidate_colour(tile.x, tile.y, v);
+                    if c != 0 {
+                        gfx.renderer.set_draw_color(palette_colour(&theme, c));
+                        let _ = gfx.renderer.fill_rect(Rect::new(
+                                x0 + (scale * x_spacing * x as u32) as i32 - scale as i32,
+                                y0 + (scale * y_spacing * y as u32) as i32 - scale as i32,
+                                scale * 3,
+                                scale * 3));
+                    }
+
+                    let colour =
+                        if !marks.assisted || board.is_unique(tile, v) {
+                            theme.candidate_unique
+                        } else {
+                            theme.candidate
+                        };
+
+                    // small numbers in the font, otherwise dots
+                    if let Some(ref mut font) = gfx.font {
+                        font.draw_char(&mut gfx.renderer, (b'0' + v) as char,
+                                candidate_xy(dst, scale, v), scale * 4, colour);
+                        continue;
                     }
 
+                    gfx.renderer.set_draw_color(colour);
                     let _ = gfx.renderer.fill_rect(Rect::new(
                             x0 + (scale * x_spacing * x as u32) as i32,
                             y0 + (scale * y_spacing * y as u32) as i32,
@@ -507,37 +1373,234 @@ impl<'a> Gui<'a> {
                 }
             }
         }
+
+        // outline the keyboard cursor
+        if marks.cursor {
+            gfx.renderer.set_draw_color(theme.cursor);
+            for i in 0..scale {
+                let _ = gfx.renderer.draw_rect(Rect::new(
+                        dst.x() - (scale - i) as i32,
+                        dst.y() - (scale - i) as i32,
+                        dst.width() + (scale - i) * 2,
+                        dst.height() + (scale - i) * 2));
+            }
+        }
+    }
+}
+
+impl<'a> Drop for Gui<'a> {
+    fn drop(&mut self) {
+        self.save_settings();
+    }
+}
+
+impl<'a> Frontend for Gui<'a> {
+    fn read_input(&mut self) -> SudokuAction {
+        self.read_event()
+    }
+
+    fn present(&mut self, board: &Board) {
+        self.start_feedback(board);
+        self.draw_to_screen(board);
+    }
+
+    fn on_action(&mut self, action: &SudokuAction) {
+        match *action {
+            SudokuAction::NoOp => return,
+
+            SudokuAction::Undo | SudokuAction::Redo => {
+                self.play(Sound::Undo);
+                self.from_history = true;
+            },
+
+            _ => {}
+        }
+
+        self.changed = true;
+    }
+
+    fn set_status(&mut self, status: &GameStatus) {
+        self.status = status.clone();
+    }
+
+    fn set_clipboard_text(&mut self, text: &str) {
+        if let Err(e) = self.clipboard.set_clipboard_text(text) {
+            eprintln!("clipboard: {}", e);
+        }
     }
 }
 
 impl GuiState {
-    fn new() -> GuiState {
+    fn new(settings: &Settings) -> GuiState {
         GuiState {
-            selected_brush: Brush::Pencil,
-            selected_value: 1
+            selected_brush: settings.brush,
+            selected_value: 1,
+            selected_colour: 1,
+            link_strong: true,
+            link_start: None,
+            cursor: None,
+            selection: Vec::new(),
+            press: None,
+            dragging: false,
+            press_candidate: 0,
+            goto_puzzle: None,
+            hover: None,
+            highlight_value: settings.highlight_value,
+            highlight_peers: settings.highlight_peers,
+            assisted_candidates: settings.assisted_candidates,
+            show_stats: false,
+            show_help: false
         }
     }
 
-    fn on_key_down(&mut self, keycode: Keycode) -> SudokuAction {
-        match keycode {
-            Keycode::N => return SudokuAction::New(None),
-            Keycode::Z => return SudokuAction::Undo,
-            Keycode::X => return SudokuAction::Redo,
+    fn on_command(&mut self, command: Command) -> SudokuAction {
+        match command {
+            Command::New => return SudokuAction::New(None),
+            Command::NextPuzzle => return SudokuAction::NextPuzzle,
+            Command::PrevPuzzle => return SudokuAction::PrevPuzzle,
+            Command::GotoPuzzle => self.goto_puzzle = Some(0),
+
+            Command::Undo => return SudokuAction::Undo,
+            Command::Redo => return SudokuAction::Redo,
+
+            Command::Pencil => self.selected_brush = Brush::Pencil,
+            Command::CrossOut => self.selected_brush = Brush::CrossOut,
 
-            Keycode::C => self.selected_brush = Brush::Pencil,
-            Keycode::V => self.selected_brush = Brush::CrossOut,
+            // the colour brush, or the next colour if already selected
+            Command::Colour =>
+                if self.selected_brush == Brush::Colour {
+                    self.selected_colour = self.selected_colour % NUM_COLOURS + 1;
+                } else {
+                    self.selected_brush = Brush::Colour;
+                },
+            Command::ClearColours => return SudokuAction::ClearColours,
+
+            // the link brush, or the other kind of link if already selected
+            Command::Link =>
+                if self.selected_brush == Brush::Link {
+                    self.link_strong = !self.link_strong;
+                } else {
+                    self.selected_brush = Brush::Link;
+                },
+            Command::ClearLinks => return SudokuAction::ClearLinks,
+
+            Command::AutoFill => return SudokuAction::AutoFill,
+            Command::Check => return SudokuAction::CheckNow,
+            Command::CheckMode => return SudokuAction::ToggleCheckMode,
+            Command::Rewind => return SudokuAction::RewindToCorrect,
+            Command::Pause => return SudokuAction::TogglePause,
+            Command::Stats => self.show_stats = !self.show_stats,
+            Command::HighlightValue => self.highlight_value = !self.highlight_value,
+            Command::HighlightPeers => self.highlight_peers = !self.highlight_peers,
+            Command::Export => return SudokuAction::Export,
+            Command::Save => return SudokuAction::SaveGame,
+            Command::Load => return SudokuAction::LoadGame,
+            Command::Copy => return SudokuAction::Copy(ExportFormat::Line),
+            Command::CopyGrid => return SudokuAction::Copy(ExportFormat::Grid),
+            Command::Help => self.show_help = !self.show_help,
+
+            // handled by Gui
+            Command::Fullscreen | Command::Theme | Command::Mute | Command::Paste => {},
+
+            // digits select a value, and assign it or toggle it at the cursor
+            Command::Value(v) => {
+                self.selected_value = v;
+                if let Some((x, y)) = self.cursor {
+                    return SudokuAction::AssignValue(x, y, v);
+                }
+            },
+
+            Command::Toggle(v) => {
+                self.selected_value = v;
+                if !self.selection.is_empty() {
+                    return SudokuAction::ToggleCrossOutMany(self.selection.clone(), v);
+                } else if let Some((x, y)) = self.cursor {
+                    return SudokuAction::ToggleCrossOut(x, y, v);
+                }
+            },
+
+            Command::PrevValue => self.on_wheel(-1),
+            Command::NextValue => self.on_wheel(1),
 
-            Keycode::A => return SudokuAction::AutoFill,
+            Command::Move(dx, dy, select) => self.move_cursor(dx as i32, dy as i32, select),
 
-            Keycode::Num1 => self.selected_value = 1,
-            Keycode::Num2 => self.selected_value = 2,
-            Keycode::Num3 => self.selected_value = 3,
-            Keycode::Num4 => self.selected_value = 4,
-            Keycode::Num5 => self.selected_value = 5,
-            Keycode::Num6 => self.selected_value = 6,
-            Keycode::Num7 => self.selected_value = 7,
-            Keycode::Num8 => self.selected_value = 8,
-            Keycode::Num9 => self.selected_value = 9,
+            Command::Cancel =>
+                if self.link_start.is_some() {
+                    self.link_start = None;
+                } else if !self.selection.is_empty() {
+                    self.selection.clear();
+                } else {
+                    self.cursor = None;
+                },
+
+            Command::Apply =>
+                match (self.selected_brush, self.cursor) {
+                    (Brush::CrossOut, _) if !self.selection.is_empty() =>
+                        return SudokuAction::CrossOutMany(
+                                self.selection.clone(), self.selected_value),
+                    (Brush::Pencil, Some((x, y))) =>
+                        return SudokuAction::AssignValue(x, y, self.selected_value),
+                    (Brush::CrossOut, Some((x, y))) =>
+                        return SudokuAction::CrossOutValue(x, y, self.selected_value),
+                    (Brush::Colour, Some((x, y))) =>
+                        return SudokuAction::ColourTile(x, y, self.selected_colour),
+                    (Brush::Link, Some((x, y))) =>
+                        return self.pick_link_end((x, y, self.selected_value)),
+                    _ => {}
+                },
+
+            Command::Clear =>
+                if !self.selection.is_empty() {
+                    return SudokuAction::UnassignMany(self.selection.clone());
+                } else if let Some((x, y)) = self.cursor {
+                    return SudokuAction::UnassignValue(x, y);
+                }
+        }
+
+        SudokuAction::NoOp
+    }
+
+    // The cursor appears in the middle of the board on the first move.
+    // Moving with shift held selects the tiles passed over.
+    fn move_cursor(&mut self, dx: i32, dy: i32, select: bool) {
+        if let (true, Some(xy)) = (select, self.cursor) {
+            self.select(xy);
+        }
+
+        self.cursor = match self.cursor {
+            None => Some((4, 4)),
+            Some((x, y)) => Some((
+                    max(0, min(x as i32 + dx, 8)) as u8,
+                    max(0, min(y as i32 + dy, 8)) as u8))
+        };
+
+        if let (true, Some(xy)) = (select, self.cursor) {
+            self.select(xy);
+        }
+    }
+
+    fn select(&mut self, xy: (u8,u8)) {
+        if !self.selection.contains(&xy) {
+            self.selection.push(xy);
+        }
+    }
+
+    fn on_goto_puzzle_key_down(&mut self, keycode: Keycode, n: usize) -> SudokuAction {
+        if let Some(d) = keycode_digit(keycode) {
+            self.goto_puzzle = Some(n.saturating_mul(10).saturating_add(d));
+            return SudokuAction::NoOp;
+        }
+
+        match keycode {
+            Keycode::Backspace => self.goto_puzzle = Some(n / 10),
+            Keycode::Escape => self.goto_puzzle = None,
+
+            Keycode::Return | Keycode::KpEnter => {
+                self.goto_puzzle = None;
+                if n > 0 {
+                    return SudokuAction::GotoPuzzle(n - 1);
+                }
+            },
 
             _ => {}
         }
@@ -545,30 +1608,117 @@ impl GuiState {
         SudokuAction::NoOp
     }
 
-    fn on_lmb(&mut self, widget: &Widget) -> SudokuAction {
+    fn on_lmb(&mut self, widget: &Widget, keymod: Mod) -> SudokuAction {
         match widget.mode {
             WidgetType::Label => {},
             WidgetType::Undo => return SudokuAction::Undo,
             WidgetType::Redo => return SudokuAction::Redo,
+            WidgetType::Timer => return SudokuAction::TogglePause,
 
-            WidgetType::Tile(x,y) =>
-                match self.selected_brush {
-                    Brush::Pencil => return SudokuAction::AssignValue(
-                            x, y, self.selected_value),
-                    Brush::CrossOut => return SudokuAction::CrossOutValue(
-                            x, y, self.selected_value)
-                },
+            // ctrl+click toggles a tile in the selection, shift+click
+            // selects the rectangle from the last tile selected
+            WidgetType::Tile(x,y) => {
+                let modifiers = Modifiers::from_mod(keymod);
+                if modifiers.ctrl {
+                    if let Some(i) = self.selection.iter().position(|&s| s == (x,y)) {
+                        self.selection.remove(i);
+                    } else {
+                        self.selection.push((x,y));
+                    }
+                } else if modifiers.shift {
+                    let (x0, y0) = self.selection.last().cloned().unwrap_or((x,y));
+                    for sy in min(y0, y)..max(y0, y)+1 {
+                        for sx in min(x0, x)..max(x0, x)+1 {
+                            self.select((sx, sy));
+                        }
+                    }
+                    // keep the corner last, for the next shift+click
+                    self.selection.retain(|&s| s != (x,y));
+                    self.selection.push((x,y));
+                } else {
+                    self.press = Some((x,y));
+                    self.dragging = false;
+                }
+            },
 
             WidgetType::ToolbarBrush(brush,_,_) =>
                 self.selected_brush = brush,
 
             WidgetType::ToolbarNumber(value) =>
-                self.selected_value = value
+                self.selected_value = value,
+
+            WidgetType::ToolbarColour(colour) => {
+                self.selected_brush = Brush::Colour;
+                self.selected_colour = colour;
+            }
         }
 
         SudokuAction::NoOp
     }
 
+    // Picks one end of a link, and returns the link once both are picked.
+    fn pick_link_end(&mut self, end: (u8,u8,u8)) -> SudokuAction {
+        match self.link_start.take() {
+            Some(start) if (start.0, start.1) != (end.0, end.1) =>
+                SudokuAction::ToggleLink(start, end, self.link_strong),
+            _ => {
+                self.link_start = Some(end);
+                SudokuAction::NoOp
+            }
+        }
+    }
+
+    // Dragging with the link brush draws a link rather than selecting.
+    fn on_drag(&mut self, x: u8, y: u8) {
+        match self.press {
+            Some(xy) if xy != (x,y) && self.selected_brush == Brush::Link =>
+                self.dragging = true,
+            Some(xy) if xy != (x,y) && !self.dragging => {
+                self.dragging = true;
+                self.selection.clear();
+                self.select(xy);
+                self.select((x,y));
+            },
+            Some(_) if self.dragging => self.select((x,y)),
+            _ => {}
+        }
+    }
+
+    // A click on a tile clears the selection and applies the brush.
+    // release is Some((x, y, candidate)) if released over a tile.
+    fn on_lmb_up(&mut self, release: Option<(u8,u8,u8)>) -> SudokuAction {
+        match self.press.take() {
+            Some((x,y)) if self.selected_brush == Brush::Link => {
+                let dragging = self.dragging;
+                self.dragging = false;
+                match release {
+                    _ if self.press_candidate == 0 => SudokuAction::NoOp,
+                    Some((x2, y2, v2)) if dragging && v2 != 0 =>
+                        SudokuAction::ToggleLink(
+                                (x, y, self.press_candidate), (x2, y2, v2), self.link_strong),
+                    _ if dragging => SudokuAction::NoOp,
+                    _ => self.pick_link_end((x, y, self.press_candidate))
+                }
+            },
+            Some((x,y)) if !self.dragging => {
+                self.selection.clear();
+                match self.selected_brush {
+                    Brush::Pencil => SudokuAction::AssignValue(x, y, self.selected_value),
+                    Brush::CrossOut => SudokuAction::CrossOutValue(x, y, self.selected_value),
+                    Brush::Colour if self.press_candidate != 0 =>
+                        SudokuAction::ColourCandidate(
+                                x, y, self.press_candidate, self.selected_colour),
+                    Brush::Colour => SudokuAction::ColourTile(x, y, self.selected_colour),
+                    Brush::Link => SudokuAction::NoOp
+                }
+            },
+            _ => {
+                self.dragging = false;
+                SudokuAction::NoOp
+            }
+        }
+    }
+
     fn on_rmb(&mut self, widget: &Widget) -> SudokuAction {
         match widget.mode {
             WidgetType::Tile(x,y) => SudokuAction::UnassignValue(x,y),
@@ -581,6 +1731,79 @@ impl GuiState {
     }
 }
 
+// The board, without its scale.
+fn board_size(m: &Metrics) -> (u32, u32) {
+    ((m.tile_w + 4) * 9 + 2, (m.tile_h + 4) * 9 + 2)
+}
+
+fn min_toolbar_width(m: &Metrics) -> u32 {
+    3
+    + m.undo_redo_w + 2 // undo
+    + m.undo_redo_w + 2 // redo
+    + TOOLBAR_TIMER_WIDTH + 2 // timer
+    + m.button_w + 2 // pencil
+    + m.button_w + 2 // erase
+    + TOOLBAR_PALETTE_WIDTH + 4 // colours
+    + (m.number_w - 1) * 9 + 1 // numbers
+    + 3
+}
+
+// Room for the board and toolbar at a scale of 1.
+fn min_screen_size(m: &Metrics) -> (u32, u32) {
+    let (board_w, board_h) = board_size(m);
+    (max(min_toolbar_width(m), board_w),
+     max(MIN_SCREEN_HEIGHT, board_h + m.button_h + 6))
+}
+
+fn palette_colour(theme: &Theme, c: u8) -> Color {
+    theme.palette[(max(c, 1) - 1) as usize % theme.palette.len()]
+}
+
+// The candidate whose dot is nearest to (x, y) in a tile drawn at scale,
+// in the layout of Gui::draw_tile, or 0 if (x, y) is not in the tile.
+fn candidate_at(dst: Rect, scale: u32, x: i32, y: i32) -> u8 {
+    let spacing = 3 * scale as i32;
+    if spacing == 0 {
+        return 0;
+    }
+
+    let dx = x - (dst.x() + dst.width() as i32 / 2);
+    let dy = y - (dst.y() + dst.height() as i32 / 2);
+
+    if dx.abs() > dst.width() as i32 / 2 || dy.abs() > dst.height() as i32 / 2 {
+        return 0;
+    }
+
+    let col = max(-1, min((2 * dx + spacing).div_euclid(2 * spacing), 1)) + 1;
+    let row = max(-1, min((2 * dy + spacing).div_euclid(2 * spacing), 1)) + 1;
+    (3 * (2 - row) + col + 1) as u8
+}
+
+// The centre of the dot of candidate v, in the layout of Gui::draw_tile.
+fn candidate_xy(dst: Rect, scale: u32, v: u8) -> (i32, i32) {
+    let col = ((v - 1) % 3) as i32;
+    let row = (2 - (v - 1) / 3) as i32;
+    let x0 = dst.x() + ((dst.width() - scale) / 2) as i32 + (scale / 2) as i32;
+    let y0 = dst.y() + ((dst.height() - scale) / 2) as i32 + (scale / 2) as i32;
+    (x0 + 3 * scale as i32 * (col - 1), y0 + 3 * scale as i32 * (row - 1))
+}
+
+fn keycode_digit(keycode: Keycode) -> Option<usize> {
+    match keycode {
+        Keycode::Num0 | Keycode::Kp0 => Some(0),
+        Keycode::Num1 | Keycode::Kp1 => Some(1),
+        Keycode::Num2 | Keycode::Kp2 => Some(2),
+        Keycode::Num3 | Keycode::Kp3 => Some(3),
+        Keycode::Num4 | Keycode::Kp4 => Some(4),
+        Keycode::Num5 | Keycode::Kp5 => Some(5),
+        Keycode::Num6 | Keycode::Kp6 => Some(6),
+        Keycode::Num7 | Keycode::Kp7 => Some(7),
+        Keycode::Num8 | Keycode::Kp8 => Some(8),
+        Keycode::Num9 | Keycode::Kp9 => Some(9),
+        _ => None
+    }
+}
+
 /*--------------------------------------------------------------*/
 
 #[cfg(not(feature = "png"))]
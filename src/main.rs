@@ -5,157 +5,140 @@ extern crate sdl2;
 #[cfg(feature = "flic")]
 extern crate flic;
 
+#[cfg(feature = "tui")]
+extern crate termion;
+
 mod action;
+mod actionlog;
+mod anim;
+mod atlas;
+mod bindings;
 mod board;
+mod cli;
+mod export;
+mod font;
+mod frontend;
+mod game;
+mod generator;
 mod gfx;
 mod gui;
+mod paths;
+mod puzzle;
+mod rating;
+mod savegame;
+mod settings;
+mod solver;
+mod sound;
+mod stats;
+mod stopwatch;
+mod theme;
 mod tile;
+mod ttf;
+
+#[cfg(feature = "tui")]
+mod tui;
 
 use std::env;
-use std::fs::File;
-use std::path::Path;
-use std::io::prelude::*;
+use std::process;
 
 use action::SudokuAction;
-use board::Board;
+use actionlog::{Recorder,Replay};
+use cli::Command;
+use frontend::Frontend;
+use game::Game;
 use gui::Gui;
-
-type History = Vec<Board>;
+use settings::Settings;
 
 fn main() {
-    let mut gui = Gui::new();
-    let mut h: History = Vec::new();
-    let mut curr_history: usize = 0;
-    let mut quit = false;
-
-    if env::args().count() > 1 {
-        let filename = env::args().nth(1).unwrap();
-        if let Some(b) = load_puzzle(&filename) {
-            h.push(b);
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match cli::parse_args(&args) {
+        Ok((Command::Play(filename, log), _)) => {
+            let settings = Settings::load();
+            run_game(Gui::new(settings.clone()), &settings, filename, log)
+        },
+        Ok((Command::Tui(filename, log), _)) => play_tui(filename, log),
+        Ok((Command::Replay(log, fast), _)) => replay(&log, fast),
+        Ok((command, format)) => process::exit(cli::run(command, format)),
+
+        Err(e) => {
+            eprintln!("{}", e);
+            eprint!("{}", cli::USAGE);
+            process::exit(2);
         }
     }
+}
 
-    if h.is_empty() {
-        h.push(Board::new());
+fn run_game<F: Frontend>(frontend: F, settings: &Settings,
+        filename: Option<String>, log: Option<String>) {
+    let mut game = Game::new();
+    let mut setup = Vec::new();
+    if settings.autofill_after_move {
+        setup.push(SudokuAction::SetAutoFillAfterMove(true));
+    }
+    if let Some(f) = filename {
+        setup.push(SudokuAction::New(Some(f)));
     }
 
-    while !quit {
-        let mut maybe_new_b: Option<Board> = None;
-        assert!(curr_history < h.len());
-
-        match gui.read_input() {
-            SudokuAction::NoOp => {},
-            SudokuAction::Quit => quit = true,
-
-            SudokuAction::New(Some(filename)) =>
-                if let Some(b) = load_puzzle(&filename) {
-                    h.clear();
-                    h.push(b);
-                    curr_history = h.len() - 1;
-                },
-
-            SudokuAction::New(None) => {
-                    h.clear();
-                    h.push(Board::new());
-                    curr_history = h.len() - 1;
-                },
-
-            SudokuAction::Undo =>
-                if curr_history > 0 {
-                    curr_history = curr_history - 1
-                },
-
-            SudokuAction::Redo =>
-                if curr_history + 1 < h.len() {
-                    curr_history = curr_history + 1
+    match log {
+        None => {
+            let mut f = frontend;
+            for a in setup {
+                game.update(a);
+            }
+            frontend::run(&mut f, &mut game);
+        },
+
+        Some(log) =>
+            match Recorder::new(frontend, &log) {
+                Ok(mut r) => {
+                    for a in setup {
+                        r.record(&a);
+                        game.update(a);
+                    }
+                    frontend::run(&mut r, &mut game);
                 },
 
-            SudokuAction::AssignValue(x,y,v) =>
-                maybe_new_b = h[curr_history].assign_value(x, y, v, false),
-
-            SudokuAction::UnassignValue(x,y) =>
-                maybe_new_b = h[curr_history].unassign_value(x, y),
-
-            SudokuAction::CrossOutValue(x,y,v) =>
-                maybe_new_b = h[curr_history].cross_out_value(x, y, v),
-
-            SudokuAction::AutoFill =>
-                maybe_new_b = h[curr_history].autofill()
-        }
-
-        if let Some(new_b) = maybe_new_b {
-            while h.len() > curr_history + 1 {
-                h.pop();
+                Err(e) => {
+                    eprintln!("{}: {}", log, e);
+                    process::exit(1);
+                }
             }
-            h.push(new_b);
-            curr_history = h.len() - 1;
-        }
-
-        gui.draw_to_screen(&h[curr_history]);
     }
 }
 
-fn load_puzzle(filename: &String) -> Option<Board> {
-    let path = Path::new(filename);
-    match File::open(path) {
-        Ok(mut f) => load_board(&mut f),
+fn replay(log: &String, fast: bool) {
+    match actionlog::load(log) {
+        Ok(actions) => {
+            let mut game = Game::without_stats();
+            let mut r = Replay::new(Gui::new(Settings::load()), actions, fast);
+            frontend::run(&mut r, &mut game);
+        },
 
         Err(e) => {
-            println!("{}: {}", filename, e);
-            None
+            eprintln!("{}: {}", log, e);
+            process::exit(1);
         }
     }
 }
 
-fn load_board(file: &mut File) -> Option<Board> {
-    let mut board = Board::new();
-    let mut x: u8 = 0;
-    let mut y: u8 = 0;
-
-    for b in file.bytes() {
-        let c = b.unwrap() as char;
-        let mut next_col = false;
-        let mut next_row = false;
-
-        match c {
-            '0' | '.' => {
-                next_col = true;
-            },
-
-            '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                let v = c.to_digit(10).unwrap() as u8;
-                if let Some(new_b) = board.assign_value(x, y, v, true) {
-                    board = new_b;
-                    next_col = true;
-                } else {
-                    break;
-                }
-            },
+/*--------------------------------------------------------------*/
 
-            '|' | '-' | ' ' | '\n' | '\t' => {
-                // ignored characters.
-            },
+#[cfg(not(feature = "tui"))]
+fn play_tui(_: Option<String>, _: Option<String>) {
+    eprintln!("tui: not supported, rebuild with --features tui");
+    process::exit(2);
+}
 
-            _ => {
-                // not a valid puzzle.
-                break;
-            }
-        }
+#[cfg(feature = "tui")]
+fn play_tui(filename: Option<String>, log: Option<String>) {
+    let settings = Settings::load();
+    match tui::Tui::new(settings.clone()) {
+        Ok(t) => run_game(t, &settings, filename, log),
 
-        if next_col {
-            x = x + 1;
-            if x >= 9 {
-                next_row = true;
-            }
-        }
-        if next_row {
-            x = 0;
-            y = y + 1;
-            if y >= 9 {
-                return Some(board)
-            }
+        Err(e) => {
+            eprintln!("tui: {}", e);
+            process::exit(1);
         }
     }
-
-    None
 }
@@ -0,0 +1,52 @@
+// paths.rs
+
+use std::env;
+use std::path::{Path,PathBuf};
+
+// $XDG_DATA_HOME/play_sudoku, or ~/.local/share/play_sudoku
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME") {
+        Some(ref d) if !d.is_empty() => Some(PathBuf::from(d)),
+        _ => env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share"))
+    };
+
+    base.or(env::var_os("APPDATA").map(PathBuf::from))
+        .map(|d| d.join("play_sudoku"))
+}
+
+// $XDG_CONFIG_HOME/play_sudoku, or ~/.config/play_sudoku
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(ref d) if !d.is_empty() => Some(PathBuf::from(d)),
+        _ => env::var_os("HOME").map(|h| PathBuf::from(h).join(".config"))
+    };
+
+    base.or(env::var_os("APPDATA").map(PathBuf::from))
+        .map(|d| d.join("play_sudoku"))
+}
+
+// The directories to look for the graphics and other data files in: those
+// in $PLAY_SUDOKU_RESOURCE_PATH, then those in the resource_path setting,
+// then the resource directory, then the directory of the executable.  The
+// defaults built in are used after these.
+pub fn resource_dirs(resource_path: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if let Some(p) = env::var_os("PLAY_SUDOKU_RESOURCE_PATH") {
+        dirs.extend(env::split_paths(&p).filter(|d| !d.as_os_str().is_empty()));
+    }
+
+    dirs.extend(resource_path.iter().cloned());
+    dirs.push(Path::new("resource").to_path_buf());
+
+    if let Some(dir) = env::current_exe().ok().and_then(|p| p.parent().map(|d| d.to_path_buf())) {
+        dirs.push(dir);
+    }
+
+    dirs
+}
+
+// name in each of the resource directories, in order.
+pub fn resource_candidates(name: &str, resource_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    resource_dirs.iter().map(|dir| dir.join(name)).collect()
+}
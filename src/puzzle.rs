@@ -0,0 +1,256 @@
+// puzzle.rs
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use board::Board;
+
+pub struct PuzzleCollection {
+    puzzles: Vec<Board>,
+    curr: usize
+}
+
+impl PuzzleCollection {
+    pub fn load(filename: &String) -> Option<PuzzleCollection> {
+        let path = Path::new(filename);
+        let mut buf = Vec::new();
+
+        if let Err(e) = File::open(path).and_then(|mut f| f.read_to_end(&mut buf)) {
+            eprintln!("{}: {}", filename, e);
+            return None;
+        }
+
+        PuzzleCollection::from_string(filename, &String::from_utf8_lossy(&buf))
+    }
+
+    // source names the origin of the contents in error messages.
+    pub fn from_string(source: &str, contents: &str) -> Option<PuzzleCollection> {
+        let puzzles = parse_puzzles(contents);
+        if puzzles.is_empty() {
+            eprintln!("{}: no puzzles found", source);
+            return None;
+        }
+
+        Some(PuzzleCollection {
+            puzzles: puzzles,
+            curr: 0
+        })
+    }
+
+    pub fn puzzles(&self) -> &Vec<Board> {
+        &self.puzzles
+    }
+
+    pub fn len(&self) -> usize {
+        self.puzzles.len()
+    }
+
+    // 0-based index of the current puzzle
+    pub fn position(&self) -> usize {
+        self.curr
+    }
+
+    pub fn current(&self) -> Board {
+        self.puzzles[self.curr].clone()
+    }
+
+    pub fn next(&mut self) -> Option<Board> {
+        let n = self.curr + 1;
+        self.goto(n)
+    }
+
+    pub fn prev(&mut self) -> Option<Board> {
+        if self.curr > 0 {
+            let n = self.curr - 1;
+            self.goto(n)
+        } else {
+            None
+        }
+    }
+
+    pub fn goto(&mut self, n: usize) -> Option<Board> {
+        if n < self.puzzles.len() {
+            self.curr = n;
+            Some(self.current())
+        } else {
+            None
+        }
+    }
+}
+
+// Reads every puzzle in the text.  Puzzles may be written one per line
+// (81 cells, anything after the 81st cell is ignored), or as grids,
+// optionally separated by blank lines.  Lines that are not part of a
+// puzzle, e.g. comments, are skipped.  A grid with a bad row is dropped
+// whole: the rest of its rows are skipped, up to the ninth or a blank line.
+pub fn parse_puzzles(contents: &str) -> Vec<Board> {
+    let mut puzzles = Vec::new();
+    let mut board = Board::new();
+    let mut n: u8 = 0;
+
+    // lines with cells read into board so far
+    let mut rows: usize = 0;
+
+    // rows of a dropped grid still to skip
+    let mut skip_rows: usize = 0;
+
+    for line in contents.lines() {
+        let cells = line.chars().filter(|&c| c == '.' || c.is_digit(10)).count();
+
+        if line.trim().is_empty() {
+            skip_rows = 0;
+            continue;
+        } else if skip_rows > 0 {
+            if cells > 0 {
+                skip_rows = skip_rows - 1;
+            }
+            continue;
+        }
+
+        let start = n;
+
+        for c in line.chars() {
+            let ok = match c {
+                '0' | '.' => {
+                    n = n + 1;
+                    true
+                },
+
+                '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
+                    // conflicting givens are kept, for validate to report
+                    let v = c.to_digit(10).unwrap() as u8;
+                    match board.assign_value(n % 9, n / 9, v, true) {
+                        Some(b) => {
+                            board = b;
+                            n = n + 1;
+                            true
+                        },
+                        None => {
+                            eprintln!("puzzle {}: cannot place {} at row {}, column {}",
+                                    puzzles.len() + 1, v, n / 9 + 1, n % 9 + 1);
+                            false
+                        }
+                    }
+                },
+
+                '|' | '-' | '+' | ' ' | '\t' | '\r' => {
+                    // ignored characters.
+                    true
+                },
+
+                _ => false
+            };
+
+            if !ok {
+                // not part of a puzzle; discard anything read so far,
+                // and if this is a row of a grid, the rest of the grid
+                // too.
+                if start > 0 || (n > start && cells <= 9) {
+                    skip_rows = 9 - (rows + 1).min(9);
+                }
+                board = Board::new();
+                n = 0;
+                rows = 0;
+                break;
+            }
+
+            if n >= 81 {
+                puzzles.push(board);
+                board = Board::new();
+                n = 0;
+                rows = 0;
+                break;
+            }
+        }
+
+        if n > start {
+            rows = rows + 1;
+        }
+    }
+
+    puzzles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_puzzles;
+    use board::Board;
+    use solver;
+    use solver::tests::UNIQUE as LINE_1;
+
+    const LINE_2: &'static str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+
+    const GRID_1: &'static str = "\
+..3|.2.|6..
+9..|3.5|..1
+..1|8.6|4..
+---+---+---
+..8|1.2|9..
+7..|...|..8
+..6|7.8|2..
+---+---+---
+..2|6.9|5..
+8..|2.3|..9
+..5|.1.|3..
+";
+
+    fn lines(puzzles: &[Board]) -> Vec<String> {
+        puzzles.iter().map(|b| solver::to_line(&solver::from_board(b, true))).collect()
+    }
+
+    #[test]
+    fn one_per_line() {
+        let text = format!("{}\n{} trailing text\n", LINE_1, LINE_2);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_1, LINE_2]);
+    }
+
+    #[test]
+    fn grids() {
+        let text = format!("{}{}", GRID_1, GRID_1);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_1, LINE_1]);
+    }
+
+    #[test]
+    fn grids_separated_by_blank_lines() {
+        let text = format!("\n{}\n\n{}\n", GRID_1, GRID_1);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_1, LINE_1]);
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let text = format!("# a collection\n{}\nPuzzle 2\n{}; the end\n", LINE_1, LINE_2);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_1, LINE_2]);
+    }
+
+    #[test]
+    fn bad_line_is_skipped() {
+        let bad = LINE_1.replace("6", "x");
+        let text = format!("{}\n{}\n", bad, LINE_2);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_2]);
+    }
+
+    #[test]
+    fn bad_grid_row_drops_the_grid() {
+        let bad = GRID_1.replace("7..|...|..8", "7..|.x.|..8");
+        let text = format!("{}{}", bad, GRID_1);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_1]);
+
+        let text = format!("{}\n{}", bad, GRID_1);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_1]);
+    }
+
+    #[test]
+    fn bad_first_grid_row_drops_the_grid() {
+        let bad = GRID_1.replacen("..3|.2.|6..", "..3|?2.|6..", 1);
+        let text = format!("{}{}", bad, GRID_1);
+        assert_eq!(lines(&parse_puzzles(&text)), vec![LINE_1]);
+    }
+
+    #[test]
+    fn conflicting_givens_are_kept() {
+        let text = format!("11{}\n", &LINE_2[2..]);
+        assert_eq!(parse_puzzles(&text).len(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+// rating.rs
+
+use std::cmp::max;
+
+use solver::{Grid,units};
+
+// Puzzles are rated by the hardest technique needed to solve them.
+#[derive(Clone,Copy,Debug,Eq,Ord,PartialEq,PartialOrd)]
+pub enum Difficulty {
+    // naked and hidden singles
+    Easy,
+
+    // locked candidates
+    Medium,
+
+    // naked and hidden pairs and triples
+    Hard,
+
+    // anything harder
+    Expert
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            "expert" => Some(Difficulty::Expert),
+            _ => None
+        }
+    }
+}
+
+// Rates a puzzle, which is assumed to have a unique solution.
+pub fn rate(grid: &Grid) -> Difficulty {
+    let mut r = Rater::new(grid);
+    let mut difficulty = Difficulty::Easy;
+
+    while !r.is_solved() {
+        let d =
+            if r.naked_single() || r.hidden_single() {
+                Difficulty::Easy
+            } else if r.locked_candidates() {
+                Difficulty::Medium
+            } else if r.naked_subset(2) || r.hidden_subset(2)
+                    || r.naked_subset(3) || r.hidden_subset(3) {
+                Difficulty::Hard
+            } else {
+                return Difficulty::Expert;
+            };
+
+        difficulty = max(difficulty, d);
+    }
+
+    difficulty
+}
+
+/*--------------------------------------------------------------*/
+
+struct Rater {
+    grid: Grid,
+    candidates: [u16; 81],
+    units: Vec<[usize; 9]>
+}
+
+impl Rater {
+    fn new(grid: &Grid) -> Rater {
+        let mut r = Rater {
+            grid: [0; 81],
+            candidates: [0x3FE; 81],
+            units: units()
+        };
+
+        for (i, &v) in grid.iter().enumerate() {
+            if v != 0 {
+                r.place(i, v);
+            }
+        }
+
+        r
+    }
+
+    fn is_solved(&self) -> bool {
+        self.grid.iter().all(|&v| v != 0)
+    }
+
+    fn place(&mut self, i: usize, v: u8) {
+        self.grid[i] = v;
+        self.candidates[i] = 0;
+
+        for u in self.units.iter() {
+            if u.contains(&i) {
+                for &j in u.iter() {
+                    self.candidates[j] = self.candidates[j] & !(1 << v);
+                }
+            }
+        }
+    }
+
+    fn naked_single(&mut self) -> bool {
+        for i in 0..81 {
+            let cs = self.candidates[i];
+            if self.grid[i] == 0 && cs.count_ones() == 1 {
+                self.place(i, cs.trailing_zeros() as u8);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn hidden_single(&mut self) -> bool {
+        for u in 0..27 {
+            for v in 1..10 {
+                let cells: Vec<usize> = self.units[u].iter().cloned()
+                    .filter(|&i| self.candidates[i] & (1 << v) != 0)
+                    .collect();
+                if cells.len() == 1 {
+                    self.place(cells[0], v);
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    // Pointing and claiming: if a value in one unit is confined to the
+    // intersection with another unit, remove it from the rest of the other.
+    fn locked_candidates(&mut self) -> bool {
+        let mut progress = false;
+
+        for a in 0..27 {
+            for b in 0..27 {
+                if a == b {
+                    continue;
+                }
+
+                for v in 1..10 {
+                    let bit = 1 << v;
+                    let in_a: Vec<usize> = self.units[a].iter().cloned()
+                        .filter(|&i| self.candidates[i] & bit != 0)
+                        .collect();
+                    if in_a.is_empty()
+                        || !in_a.iter().all(|i| self.units[b].contains(i)) {
+                        continue;
+                    }
+
+                    for &j in self.units[b].iter() {
+                        if !self.units[a].contains(&j) && self.candidates[j] & bit != 0 {
+                            self.candidates[j] = self.candidates[j] & !bit;
+                            progress = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        progress
+    }
+
+    // n tiles in a unit with only n candidates between them.
+    fn naked_subset(&mut self, n: usize) -> bool {
+        let mut progress = false;
+
+        for u in 0..27 {
+            let cells: Vec<usize> = self.units[u].iter().cloned()
+                .filter(|&i| self.grid[i] == 0)
+                .collect();
+
+            for subset in combinations(cells.len(), n) {
+                let mask = subset.iter().fold(0, |m, &k| m | self.candidates[cells[k]]);
+                if mask.count_ones() as usize != n {
+                    continue;
+                }
+
+                for (k, &j) in cells.iter().enumerate() {
+                    if !subset.contains(&k) && self.candidates[j] & mask != 0 {
+                        self.candidates[j] = self.candidates[j] & !mask;
+                        progress = true;
+                    }
+                }
+            }
+        }
+
+        progress
+    }
+
+    // n values in a unit confined to n tiles.
+    fn hidden_subset(&mut self, n: usize) -> bool {
+        let mut progress = false;
+
+        for u in 0..27 {
+            let values: Vec<u8> = (1..10)
+                .filter(|&v| self.units[u].iter().any(|&i| self.candidates[i] & (1 << v) != 0))
+                .collect();
+
+            for subset in combinations(values.len(), n) {
+                let mask = subset.iter().fold(0u16, |m, &k| m | (1 << values[k]));
+                let cells: Vec<usize> = self.units[u].iter().cloned()
+                    .filter(|&i| self.candidates[i] & mask != 0)
+                    .collect();
+                if cells.len() != n {
+                    continue;
+                }
+
+                for &j in cells.iter() {
+                    if self.candidates[j] & !mask != 0 {
+                        self.candidates[j] = self.candidates[j] & mask;
+                        progress = true;
+                    }
+                }
+            }
+        }
+
+        progress
+    }
+}
+
+// all n-element subsets of 0..len
+fn combinations(len: usize, n: usize) -> Vec<Vec<usize>> {
+    let mut cs = Vec::new();
+    let mut c: Vec<usize> = (0..n).collect();
+
+    if n == 0 || n > len {
+        return cs;
+    }
+
+    loop {
+        cs.push(c.clone());
+
+        let mut k = n;
+        while k > 0 && c[k - 1] == len - n + k - 1 {
+            k = k - 1;
+        }
+        if k == 0 {
+            return cs;
+        }
+
+        c[k - 1] = c[k - 1] + 1;
+        for j in k..n {
+            c[j] = c[j - 1] + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::tests::{UNIQUE,grid};
+
+    #[test]
+    fn singles_are_easy() {
+        assert_eq!(rate(&grid(UNIQUE)), Difficulty::Easy);
+    }
+
+    #[test]
+    fn beyond_subsets_is_expert() {
+        let grid = grid(
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..");
+        assert_eq!(rate(&grid), Difficulty::Expert);
+    }
+
+    #[test]
+    fn names() {
+        for &d in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert].iter() {
+            assert_eq!(Difficulty::from_name(d.name()), Some(d));
+        }
+        assert_eq!(Difficulty::from_name("impossible"), None);
+    }
+}
@@ -0,0 +1,231 @@
+// savegame.rs
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use board::Board;
+use paths;
+use puzzle::parse_puzzles;
+use solver;
+
+// A save game holds the current position, with the player's colours and
+// links, and the time spent on it, e.g.
+//
+//   # play_sudoku save game
+//   givens ..............3.85..1.2...
+//   values ..............3.85..1.2...
+//   eliminated 0 0 26
+//   colour 4 5 2
+//   candidate-colour 0 0 7 1
+//   link strong 0 0 7 0 5 7
+//   elapsed 123456
+
+pub struct SaveGame {
+    pub board: Board,
+
+    // milliseconds spent solving
+    pub elapsed: u64
+}
+
+pub fn save_path() -> Option<PathBuf> {
+    paths::data_dir().map(|d| d.join("save.txt"))
+}
+
+pub fn save(board: &Board, elapsed: u64) -> io::Result<()> {
+    let path = save_path().ok_or(
+            io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    write(&mut File::create(&path)?, board, elapsed)
+}
+
+fn write<W: Write>(f: &mut W, board: &Board, elapsed: u64) -> io::Result<()> {
+    writeln!(f, "# play_sudoku save game")?;
+    writeln!(f, "givens {}", solver::to_line(&solver::from_board(board, true)))?;
+    writeln!(f, "values {}", solver::to_line(&solver::from_board(board, false)))?;
+
+    for y in 0..9 {
+        for x in 0..9 {
+            if let Some(t) = board.get(x, y) {
+                if !t.eliminated.is_empty() {
+                    let vs: String = t.eliminated.iter().map(|&v| (b'0' + v) as char).collect();
+                    writeln!(f, "eliminated {} {} {}", x, y, vs)?;
+                }
+            }
+        }
+    }
+
+    for y in 0..9 {
+        for x in 0..9 {
+            let c = board.tile_colour(x, y);
+            if c != 0 {
+                writeln!(f, "colour {} {} {}", x, y, c)?;
+            }
+
+            for v in 1..10 {
+                let c = board.candidate_colour(x, y, v);
+                if c != 0 {
+                    writeln!(f, "candidate-colour {} {} {} {}", x, y, v, c)?;
+                }
+            }
+        }
+    }
+
+    for l in board.links().iter() {
+        let ((x1, y1, v1), (x2, y2, v2)) = (l.from, l.to);
+        writeln!(f, "link {} {} {} {} {} {} {}",
+                if l.strong { "strong" } else { "weak" }, x1, y1, v1, x2, y2, v2)?;
+    }
+
+    writeln!(f, "elapsed {}", elapsed)?;
+    Ok(())
+}
+
+pub fn load() -> io::Result<SaveGame> {
+    parse(&read()?)
+}
+
+// The contents of the save game file.
+pub fn read() -> io::Result<String> {
+    let path = save_path().ok_or(
+            io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let mut contents = String::new();
+    File::open(&path)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+pub fn parse(contents: &str) -> io::Result<SaveGame> {
+    let mut givens: Option<Board> = None;
+    let mut values = String::new();
+    let mut eliminated: Vec<(u8,u8,u8)> = Vec::new();
+    let mut colours: Vec<(u8,u8,u8)> = Vec::new();
+    let mut candidate_colours: Vec<(u8,u8,u8,u8)> = Vec::new();
+    let mut links: Vec<((u8,u8,u8),(u8,u8,u8),bool)> = Vec::new();
+    let mut elapsed = 0;
+
+    for line in contents.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let nums: Vec<u8> = words.iter().skip(1).filter_map(|w| w.parse::<u8>().ok()).collect();
+
+        match words.first().map(|w| *w) {
+            Some("givens") if words.len() == 2 =>
+                givens = parse_puzzles(words[1]).pop(),
+
+            Some("values") if words.len() == 2 =>
+                values = String::from(words[1]),
+
+            Some("eliminated") if words.len() == 4 =>
+                if let (Ok(x), Ok(y)) = (words[1].parse::<u8>(), words[2].parse::<u8>()) {
+                    for c in words[3].chars() {
+                        if let Some(v) = c.to_digit(10) {
+                            eliminated.push((x, y, v as u8));
+                        }
+                    }
+                },
+
+            Some("colour") if words.len() == 4 && nums.len() == 3 =>
+                colours.push((nums[0], nums[1], nums[2])),
+
+            Some("candidate-colour") if words.len() == 5 && nums.len() == 4 =>
+                candidate_colours.push((nums[0], nums[1], nums[2], nums[3])),
+
+            Some("link") if words.len() == 8 && nums.len() == 6 =>
+                match words[1] {
+                    "strong" | "weak" =>
+                        links.push(((nums[0], nums[1], nums[2]), (nums[3], nums[4], nums[5]),
+                                words[1] == "strong")),
+                    _ => {}
+                },
+
+            Some("elapsed") if words.len() == 2 =>
+                elapsed = words[1].parse::<u64>().unwrap_or(0),
+
+            _ => {}
+        }
+    }
+
+    let mut board = match givens {
+        Some(b) => b,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "no puzzle"))
+    };
+
+    // cross out before assigning the player's values, while the
+    // crossed out values are still candidates.
+    for &(x, y, v) in eliminated.iter() {
+        if let Some(b) = board.cross_out_value(x, y, v) {
+            board = b;
+        }
+    }
+
+    for (i, c) in values.chars().enumerate().take(81) {
+        let (x, y) = ((i % 9) as u8, (i / 9) as u8);
+        let is_given = board.get(x, y).map_or(true, |t| t.is_init());
+
+        if let (Some(v), false) = (c.to_digit(10), is_given) {
+            if v != 0 {
+                if let Some(b) = board.assign_value(x, y, v as u8, false) {
+                    board = b;
+                }
+            }
+        }
+    }
+
+    // candidates can only be coloured and linked once the values are in
+    for &(x, y, c) in colours.iter() {
+        if let Some(b) = board.colour_tile(x, y, c) {
+            board = b;
+        }
+    }
+
+    for &(x, y, v, c) in candidate_colours.iter() {
+        if let Some(b) = board.colour_candidate(x, y, v, c) {
+            board = b;
+        }
+    }
+
+    for &(a, b, strong) in links.iter() {
+        if let Some(bd) = board.toggle_link(a, b, strong) {
+            board = bd;
+        }
+    }
+
+    Ok(SaveGame {
+        board: board,
+        elapsed: elapsed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse,write};
+    use puzzle::parse_puzzles;
+    use solver::tests::UNIQUE;
+
+    #[test]
+    fn annotations_are_kept() {
+        let b = parse_puzzles(UNIQUE).pop().unwrap();
+        let b = b.assign_value(0, 0, 4, false).unwrap()
+            .cross_out_value(1, 0, 5).unwrap()
+            .colour_tile(4, 4, 2).unwrap()
+            .colour_candidate(1, 0, 8, 1).unwrap()
+            .toggle_link((1, 0, 8), (1, 1, 8), true).unwrap()
+            .toggle_link((1, 0, 7), (7, 0, 7), false).unwrap();
+
+        let mut buf = Vec::new();
+        write(&mut buf, &b, 1234).unwrap();
+        let sg = parse(&String::from_utf8(buf).unwrap()).unwrap();
+
+        assert_eq!(sg.elapsed, 1234);
+        assert_eq!(sg.board.get(0, 0).unwrap().assignment, Some(4));
+        assert_eq!(sg.board.get(1, 0).unwrap().eliminated, vec![5]);
+        assert_eq!(sg.board.tile_colour(4, 4), 2);
+        assert_eq!(sg.board.candidate_colour(1, 0, 8), 1);
+        assert!(*sg.board.links() == *b.links());
+        assert_eq!(sg.board.links().len(), 2);
+    }
+}
@@ -0,0 +1,238 @@
+// settings.rs
+
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use frontend::Brush;
+use paths;
+
+// The settings file holds one setting per line, e.g.
+//
+//   window_width = 640
+//   window_height = 400
+//   fullscreen = false
+//   smooth_scaling = true
+//   animations = true
+//   mute = false
+//   volume = 100
+//   brush = pencil
+//   assisted_candidates = true
+//   highlight_value = true
+//   highlight_peers = true
+//   autofill = manual
+//   theme = light
+//   font = /usr/share/fonts/truetype/dejavu/DejaVuSans.ttf
+//   resource_path = /usr/share/play_sudoku:/usr/local/share/play_sudoku
+//
+// Settings left out keep their defaults.  The file is rewritten when a
+// setting is changed in the game.
+
+pub const DEFAULT_WINDOW_WIDTH: u32 = 640;
+pub const DEFAULT_WINDOW_HEIGHT: u32 = 400;
+
+#[derive(Clone,PartialEq)]
+pub struct Settings {
+    // the window size when not fullscreen
+    pub window_width: u32,
+    pub window_height: u32,
+    pub fullscreen: bool,
+
+    // stretch the board to fill the window, rather than only by whole
+    // numbers of pixels
+    pub smooth_scaling: bool,
+
+    // play the animations in pop.flc, shake.flc, sweep.flc and win.flc
+    pub animations: bool,
+
+    // the sound effects, if built with the sound feature, and their volume
+    // in percent
+    pub mute: bool,
+    pub volume: u32,
+
+    pub brush: Brush,
+
+    // darken the candidates that are the only place left for their value
+    pub assisted_candidates: bool,
+
+    // shade the tiles holding the selected value, and tint the peers of
+    // the focused tile
+    pub highlight_value: bool,
+    pub highlight_peers: bool,
+
+    // fill in the forced values after every move, rather than on request
+    pub autofill_after_move: bool,
+
+    pub theme: String,
+
+    // Some(TrueType font) to draw the numbers and text with, if built with
+    // the ttf feature
+    pub font: Option<PathBuf>,
+
+    // directories to look in first for the graphics, see
+    // paths::resource_dirs
+    pub resource_path: Vec<PathBuf>
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings {
+            window_width: DEFAULT_WINDOW_WIDTH,
+            window_height: DEFAULT_WINDOW_HEIGHT,
+            fullscreen: false,
+            smooth_scaling: true,
+            animations: true,
+            mute: false,
+            volume: 100,
+            brush: Brush::Pencil,
+            assisted_candidates: true,
+            highlight_value: true,
+            highlight_peers: true,
+            autofill_after_move: false,
+            theme: String::from("light"),
+            font: None,
+            resource_path: Vec::new()
+        }
+    }
+
+    // The defaults, then the settings file if there is one.
+    pub fn load() -> Settings {
+        let mut s = Settings::new();
+
+        if let Some(path) = settings_path() {
+            if let Err(e) = s.load_file(&path) {
+                eprintln!("{}: {}", path.display(), e);
+            }
+        }
+
+        s
+    }
+
+    fn load_file(&mut self, path: &PathBuf) -> io::Result<()> {
+        let f = match File::open(path) {
+            Ok(f) => BufReader::new(f),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e)
+        };
+
+        for (i, line) in f.lines().enumerate() {
+            if let Err(e) = self.parse_line(&line?) {
+                eprintln!("{}:{}: {}", path.display(), i + 1, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (key, value) = match line.find('=') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(format!("expected <setting> = <value>: {}", line))
+        };
+
+        let bad_value = || format!("{}: bad value: {}", key, value);
+        let number = || value.parse::<u32>().map_err(|_| bad_value());
+        let flag = || match value {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(bad_value())
+        };
+
+        match key {
+            "window_width" => self.window_width = number()?,
+            "window_height" => self.window_height = number()?,
+            "fullscreen" => self.fullscreen = flag()?,
+            "smooth_scaling" => self.smooth_scaling = flag()?,
+            "animations" => self.animations = flag()?,
+            "mute" => self.mute = flag()?,
+            "volume" => self.volume = number()?,
+            "brush" => self.brush = brush_from_name(value).ok_or(bad_value())?,
+            "assisted_candidates" => self.assisted_candidates = flag()?,
+            "highlight_value" => self.highlight_value = flag()?,
+            "highlight_peers" => self.highlight_peers = flag()?,
+
+            "autofill" =>
+                self.autofill_after_move = match value {
+                    "manual" => false,
+                    "after-move" => true,
+                    _ => return Err(bad_value())
+                },
+
+            "theme" => self.theme = String::from(value),
+
+            "font" =>
+                self.font = if value.is_empty() { None } else { Some(PathBuf::from(value)) },
+
+            "resource_path" =>
+                self.resource_path =
+                    env::split_paths(value).filter(|d| !d.as_os_str().is_empty()).collect(),
+
+            _ => return Err(format!("unknown setting: {}", key))
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = settings_path().ok_or(
+                io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut f = File::create(&path)?;
+        writeln!(f, "window_width = {}", self.window_width)?;
+        writeln!(f, "window_height = {}", self.window_height)?;
+        writeln!(f, "fullscreen = {}", self.fullscreen)?;
+        writeln!(f, "smooth_scaling = {}", self.smooth_scaling)?;
+        writeln!(f, "animations = {}", self.animations)?;
+        writeln!(f, "mute = {}", self.mute)?;
+        writeln!(f, "volume = {}", self.volume)?;
+        writeln!(f, "brush = {}", brush_name(self.brush))?;
+        writeln!(f, "assisted_candidates = {}", self.assisted_candidates)?;
+        writeln!(f, "highlight_value = {}", self.highlight_value)?;
+        writeln!(f, "highlight_peers = {}", self.highlight_peers)?;
+        writeln!(f, "autofill = {}",
+                if self.autofill_after_move { "after-move" } else { "manual" })?;
+        writeln!(f, "theme = {}", self.theme)?;
+        writeln!(f, "font = {}",
+                self.font.as_ref().map_or(String::new(), |p| p.display().to_string()))?;
+        let resource_path = env::join_paths(self.resource_path.iter())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        writeln!(f, "resource_path = {}", resource_path.to_string_lossy())?;
+
+        Ok(())
+    }
+}
+
+pub fn settings_path() -> Option<PathBuf> {
+    paths::config_dir().map(|d| d.join("settings.txt"))
+}
+
+fn brush_name(brush: Brush) -> &'static str {
+    match brush {
+        Brush::Pencil => "pencil",
+        Brush::CrossOut => "cross-out",
+        Brush::Colour => "colour",
+        Brush::Link => "link"
+    }
+}
+
+fn brush_from_name(name: &str) -> Option<Brush> {
+    match name {
+        "pencil" => Some(Brush::Pencil),
+        "cross-out" => Some(Brush::CrossOut),
+        "colour" => Some(Brush::Colour),
+        "link" => Some(Brush::Link),
+        _ => None
+    }
+}
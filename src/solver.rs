@@ -0,0 +1,312 @@
+// solver.rs
+
+use std::time::{SystemTime,UNIX_EPOCH};
+
+use board::Board;
+
+// values of the 81 tiles in row-major order, 0 for unassigned
+pub type Grid = [u8; 81];
+
+const ALL_CANDIDATES: u16 = 0x3FE;
+
+pub fn from_board(board: &Board, givens_only: bool) -> Grid {
+    let mut grid = [0; 81];
+
+    for y in 0..9 {
+        for x in 0..9 {
+            if let Some(t) = board.get(x, y) {
+                if !givens_only || t.is_init() {
+                    grid[9 * y as usize + x as usize] = t.assignment.unwrap_or(0);
+                }
+            }
+        }
+    }
+
+    grid
+}
+
+pub fn to_line(grid: &Grid) -> String {
+    grid.iter().map(|&v| if v == 0 { '.' } else { (b'0' + v) as char }).collect()
+}
+
+// the row, column and block of every tile
+pub fn units() -> Vec<[usize; 9]> {
+    let mut us = Vec::new();
+
+    for r in 0..9 {
+        let mut u = [0; 9];
+        for c in 0..9 {
+            u[c] = 9 * r + c;
+        }
+        us.push(u);
+    }
+
+    for c in 0..9 {
+        let mut u = [0; 9];
+        for r in 0..9 {
+            u[r] = 9 * r + c;
+        }
+        us.push(u);
+    }
+
+    for b in 0..9 {
+        let mut u = [0; 9];
+        for i in 0..9 {
+            u[i] = 9 * (3 * (b / 3) + i / 3) + 3 * (b % 3) + i % 3;
+        }
+        us.push(u);
+    }
+
+    us
+}
+
+// true if no value appears twice in a row, column or block.
+pub fn is_consistent(grid: &Grid) -> bool {
+    units().iter().all(|u| {
+            let mut seen: u16 = 0;
+            for &i in u.iter() {
+                let bit = 1 << grid[i];
+                if grid[i] != 0 && seen & bit != 0 {
+                    return false;
+                }
+                seen = seen | bit;
+            }
+            true
+        })
+}
+
+pub fn solve(grid: &Grid) -> Option<Grid> {
+    let mut solution = None;
+    let mut s = Search::new(grid)?;
+    s.search(&mut |g| { solution = Some(*g); true }, None);
+    solution
+}
+
+// Counts the solutions, stopping at limit, or never if limit is 0.
+pub fn count_solutions(grid: &Grid, limit: usize) -> usize {
+    let mut count = 0;
+
+    if let Some(mut s) = Search::new(grid) {
+        s.search(&mut |_| { count = count + 1; limit > 0 && count >= limit }, None);
+    }
+
+    count
+}
+
+pub fn has_unique_solution(grid: &Grid) -> bool {
+    count_solutions(grid, 2) == 1
+}
+
+pub fn random_solution(rng: &mut Rng) -> Grid {
+    let mut solution = [0; 81];
+    let mut s = Search::new(&[0; 81]).unwrap();
+    s.search(&mut |g| { solution = *g; true }, Some(rng));
+    solution
+}
+
+/*--------------------------------------------------------------*/
+
+struct Search {
+    grid: Grid,
+    rows: [u16; 9],
+    cols: [u16; 9],
+    blocks: [u16; 9]
+}
+
+impl Search {
+    fn new(grid: &Grid) -> Option<Search> {
+        let mut s = Search {
+            grid: [0; 81],
+            rows: [0; 9],
+            cols: [0; 9],
+            blocks: [0; 9]
+        };
+
+        for (i, &v) in grid.iter().enumerate() {
+            if v != 0 {
+                if v > 9 || s.candidates(i) & (1 << v) == 0 {
+                    return None;
+                }
+                s.set(i, v);
+            }
+        }
+
+        Some(s)
+    }
+
+    fn candidates(&self, i: usize) -> u16 {
+        let (r, c) = (i / 9, i % 9);
+        !(self.rows[r] | self.cols[c] | self.blocks[3 * (r / 3) + c / 3]) & ALL_CANDIDATES
+    }
+
+    fn set(&mut self, i: usize, v: u8) {
+        let (r, c) = (i / 9, i % 9);
+        let bit = 1 << v;
+        self.grid[i] = v;
+        self.rows[r] = self.rows[r] | bit;
+        self.cols[c] = self.cols[c] | bit;
+        self.blocks[3 * (r / 3) + c / 3] = self.blocks[3 * (r / 3) + c / 3] | bit;
+    }
+
+    fn clear(&mut self, i: usize) {
+        let (r, c) = (i / 9, i % 9);
+        let bit = !(1 << self.grid[i]);
+        self.grid[i] = 0;
+        self.rows[r] = self.rows[r] & bit;
+        self.cols[c] = self.cols[c] & bit;
+        self.blocks[3 * (r / 3) + c / 3] = self.blocks[3 * (r / 3) + c / 3] & bit;
+    }
+
+    // Calls found with each solution until it returns true.
+    // Returns true if the search was stopped.
+    fn search<F>(&mut self, found: &mut F, mut rng: Option<&mut Rng>) -> bool
+            where F: FnMut(&Grid) -> bool {
+        // branch on the tile with the fewest candidates
+        let mut best: Option<(usize, u16)> = None;
+        for i in 0..81 {
+            if self.grid[i] == 0 {
+                let cs = self.candidates(i);
+                if best.map_or(true, |(_, b)| cs.count_ones() < b.count_ones()) {
+                    best = Some((i, cs));
+                    if cs.count_ones() <= 1 {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let (i, cs) = match best {
+            None => return found(&self.grid),
+            Some(b) => b
+        };
+
+        let mut vs: Vec<u8> = (1..10).filter(|&v| cs & (1 << v) != 0).collect();
+        if let Some(ref mut r) = rng {
+            r.shuffle(&mut vs);
+        }
+
+        for v in vs {
+            self.set(i, v);
+            let stop = match rng {
+                Some(ref mut r) => self.search(found, Some(&mut **r)),
+                None => self.search(found, None)
+            };
+            self.clear(i);
+
+            if stop {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/*--------------------------------------------------------------*/
+
+// xorshift64*, good enough for shuffling puzzles.
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed }
+        }
+    }
+
+    pub fn from_time() -> Rng {
+        let seed = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() ^ ((d.subsec_nanos() as u64) << 32),
+            Err(_) => 0
+        };
+        Rng::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state ^ (self.state >> 12);
+        self.state = self.state ^ (self.state << 25);
+        self.state = self.state ^ (self.state >> 27);
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // a random number in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % (n as u64)) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, xs: &mut [T]) {
+        for i in (1..xs.len()).rev() {
+            let j = self.below(i + 1);
+            xs.swap(i, j);
+        }
+    }
+}
+
+// The fixtures are shared with the tests of the other modules.
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // a puzzle with one solution, which singles alone solve
+    pub const UNIQUE: &'static str =
+        "..3.2.6..9..3.5..1..18.64....81.29..7.......8..67.82....26.95..8..2.3..9..5.1.3..";
+    pub const SOLUTION: &'static str =
+        "483921657967345821251876493548132976729564138136798245372689514814253769695417382";
+
+    // 81 cells, '.' or '0' for blanks
+    pub fn grid(line: &str) -> Grid {
+        let mut grid = [0; 81];
+        for (i, c) in line.chars().enumerate() {
+            grid[i] = c.to_digit(10).unwrap_or(0) as u8;
+        }
+        grid
+    }
+
+    #[test]
+    fn unique_puzzle() {
+        let grid = grid(UNIQUE);
+        assert!(is_consistent(&grid));
+        assert_eq!(count_solutions(&grid, 0), 1);
+        assert!(has_unique_solution(&grid));
+        assert_eq!(solve(&grid).map(|g| to_line(&g)), Some(String::from(SOLUTION)));
+    }
+
+    #[test]
+    fn multiple_solutions() {
+        // the solution with a rectangle of 8s and 6s in the top two rows
+        // taken out, which can go either way round
+        let mut grid = grid(SOLUTION);
+        for &i in [1, 6, 10, 15].iter() {
+            grid[i] = 0;
+        }
+        assert_eq!(count_solutions(&grid, 0), 2);
+        assert_eq!(count_solutions(&grid, 1), 1);
+        assert!(!has_unique_solution(&grid));
+        assert_eq!(count_solutions(&[0; 81], 5), 5);
+    }
+
+    #[test]
+    fn invalid_puzzles() {
+        // two 3s in the top row
+        let conflicting = grid(&format!("3{}", &UNIQUE[1..]));
+        assert!(!is_consistent(&conflicting));
+        assert_eq!(count_solutions(&conflicting, 0), 0);
+        assert!(solve(&conflicting).is_none());
+
+        // the top right tile can only be 9, which its column already has
+        let unsolvable = grid(&format!("12345678.{}9", ".".repeat(8)));
+        assert!(is_consistent(&unsolvable));
+        assert_eq!(count_solutions(&unsolvable, 0), 0);
+        assert!(solve(&unsolvable).is_none());
+    }
+
+    #[test]
+    fn random_solutions_are_complete() {
+        let mut rng = Rng::new(1);
+        let grid = random_solution(&mut rng);
+        assert!(grid.iter().all(|&v| v != 0));
+        assert!(is_consistent(&grid));
+    }
+}
@@ -0,0 +1,215 @@
+// sound.rs
+
+// Sound effects through SDL audio, with the sound feature: place.wav,
+// cross-out.wav, conflict.wav, undo.wav, unit.wav and solved.wav, looked
+// for like the graphics, or the copies built in.  Sounds are converted to
+// the device's format as they are loaded, and mixed as they play so that
+// one does not cut another off.
+
+use std::path::PathBuf;
+use sdl2::Sdl;
+
+use board::Change;
+
+#[cfg(feature = "sound")]
+use std::cmp::min;
+#[cfg(feature = "sound")]
+use sdl2::audio::{AudioCallback,AudioCVT,AudioDevice,AudioSpec,AudioSpecDesired,AudioSpecWAV};
+#[cfg(feature = "sound")]
+use sdl2::rwops::RWops;
+#[cfg(feature = "sound")]
+use paths;
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Sound {
+    Place,
+    CrossOut,
+    Conflict,
+    Undo,
+    UnitCompleted,
+    Solved
+}
+
+#[cfg(feature = "sound")]
+const SOUNDS: [Sound; 6] = [Sound::Place, Sound::CrossOut, Sound::Conflict,
+    Sound::Undo, Sound::UnitCompleted, Sound::Solved];
+
+#[cfg(feature = "sound")]
+pub struct Sounds {
+    device: AudioDevice<Mixer>
+}
+
+// Without the sound feature, there are never any sounds.
+#[cfg(not(feature = "sound"))]
+pub enum Sounds {}
+
+#[cfg(feature = "sound")]
+struct Mixer {
+    // the samples of each sound, in the order of SOUNDS
+    sounds: Vec<Vec<i16>>,
+
+    // (sound, samples played so far) of the sounds playing
+    playing: Vec<(usize, usize)>,
+
+    // percent
+    volume: i32
+}
+
+// The sound for the changes to a board, the most important if there are
+// several, e.g. only the solved sound for the last number.
+pub fn from_changes(changes: &[Change]) -> Option<Sound> {
+    let rank = |c: &Change| match *c {
+        Change::Solved => (5, Sound::Solved),
+        Change::UnitCompleted(_) => (4, Sound::UnitCompleted),
+        Change::Conflict(..) => (3, Sound::Conflict),
+        Change::Assigned(..) => (2, Sound::Place),
+        Change::CrossedOut(..) => (1, Sound::CrossOut)
+    };
+
+    changes.iter().map(rank).max_by_key(|&(r, _)| r).map(|(_, s)| s)
+}
+
+#[cfg(feature = "sound")]
+impl Sounds {
+    // None if there is no audio device.  volume is a percentage.
+    pub fn new(sdl: &Sdl, resource_dirs: &[PathBuf], volume: u32) -> Option<Sounds> {
+        let audio = match sdl.audio() {
+            Ok(a) => a,
+            Err(e) => {
+                eprintln!("audio: {}", e);
+                return None;
+            }
+        };
+
+        let desired = AudioSpecDesired { freq: Some(22050), channels: Some(1), samples: Some(512) };
+        let device = audio.open_playback(None, &desired, |spec| Mixer {
+            sounds: SOUNDS.iter().map(|&s| load_sound(s, resource_dirs, &spec)).collect(),
+            playing: Vec::new(),
+            volume: min(volume, 100) as i32
+        });
+
+        match device {
+            Ok(device) => {
+                device.resume();
+                Some(Sounds { device: device })
+            },
+            Err(e) => {
+                eprintln!("audio: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        if let Some(i) = SOUNDS.iter().position(|&s| s == sound) {
+            self.device.lock().playing.push((i, 0));
+        }
+    }
+}
+
+#[cfg(not(feature = "sound"))]
+impl Sounds {
+    pub fn new(_: &Sdl, _: &[PathBuf], _: u32) -> Option<Sounds> {
+        None
+    }
+
+    pub fn play(&mut self, _: Sound) {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "sound")]
+impl AudioCallback for Mixer {
+    type Channel = i16;
+
+    fn callback(&mut self, out: &mut [i16]) {
+        for s in out.iter_mut() {
+            *s = 0;
+        }
+
+        let sounds = &self.sounds;
+        for &mut (i, ref mut pos) in self.playing.iter_mut() {
+            let samples = &sounds[i][*pos..];
+            for (o, &s) in out.iter_mut().zip(samples.iter()) {
+                let mixed = *o as i32 + s as i32 * self.volume / 100;
+                *o = mixed.max(-32768).min(32767) as i16;
+            }
+            *pos += min(samples.len(), out.len());
+        }
+
+        self.playing.retain(|&(i, pos)| pos < sounds[i].len());
+    }
+}
+
+/*--------------------------------------------------------------*/
+
+#[cfg(feature = "sound")]
+impl Sound {
+    fn file_name(&self) -> &'static str {
+        match *self {
+            Sound::Place => "place.wav",
+            Sound::CrossOut => "cross-out.wav",
+            Sound::Conflict => "conflict.wav",
+            Sound::Undo => "undo.wav",
+            Sound::UnitCompleted => "unit.wav",
+            Sound::Solved => "solved.wav"
+        }
+    }
+
+    fn embedded(&self) -> &'static [u8] {
+        match *self {
+            Sound::Place => include_bytes!("../resource/place.wav"),
+            Sound::CrossOut => include_bytes!("../resource/cross-out.wav"),
+            Sound::Conflict => include_bytes!("../resource/conflict.wav"),
+            Sound::Undo => include_bytes!("../resource/undo.wav"),
+            Sound::UnitCompleted => include_bytes!("../resource/unit.wav"),
+            Sound::Solved => include_bytes!("../resource/solved.wav")
+        }
+    }
+}
+
+// A file in the resource directories, or the copy built in, converted to
+// the format of spec.  A sound that cannot be loaded is silent.
+#[cfg(feature = "sound")]
+fn load_sound(sound: Sound, resource_dirs: &[PathBuf], spec: &AudioSpec) -> Vec<i16> {
+    let name = sound.file_name();
+    let file = paths::resource_candidates(name, resource_dirs).iter()
+        .filter(|path| path.exists())
+        .filter_map(|path| match AudioSpecWAV::load_wav(path) {
+            Ok(wav) => Some(wav),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                None
+            }
+        })
+        .next();
+
+    let wav = match file {
+        Some(wav) => Some(wav),
+        None => RWops::from_bytes(sound.embedded()).ok()
+            .and_then(|mut rw| AudioSpecWAV::load_wav_rw(&mut rw).ok())
+    };
+
+    let cvt = wav.as_ref().map(
+            |wav| AudioCVT::new(wav.format, wav.channels, wav.freq,
+                    spec.format, spec.channels, spec.freq));
+
+    match (wav, cvt) {
+        (Some(wav), Some(Ok(cvt))) => {
+            // the device is opened for i16 samples, so they are in the
+            // native byte order
+            let bytes = cvt.convert(wav.buffer().to_vec());
+            bytes.chunks(2).filter(|b| b.len() == 2)
+                .map(|b| i16::from_ne_bytes([b[0], b[1]]))
+                .collect()
+        },
+        (_, Some(Err(e))) => {
+            eprintln!("{}: {}", name, e);
+            Vec::new()
+        },
+        _ => {
+            eprintln!("{}: not loaded", name);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// stats.rs
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::time::{SystemTime,UNIX_EPOCH};
+
+use board::Board;
+use paths;
+use solver;
+
+// The statistics file holds one line per puzzle played, keyed by a hash
+// of the givens, e.g.
+//
+//   # puzzle attempts completions best_ms hints last_played
+//   9f3c2a61d0b4e857 5 2 201234 3 2026-10-19
+//
+// best_ms is '-' until the puzzle has been solved.
+
+#[derive(Clone)]
+pub struct PuzzleStats {
+    pub attempts: u32,
+    pub completions: u32,
+
+    // Some(fastest solve in milliseconds)
+    pub best: Option<u64>,
+
+    // auto-fills, checks and rewinds used, over all attempts
+    pub hints: u32,
+
+    // yyyy-mm-dd, UTC
+    pub last_played: String
+}
+
+pub type Stats = BTreeMap<String, PuzzleStats>;
+
+impl PuzzleStats {
+    pub fn new() -> PuzzleStats {
+        PuzzleStats {
+            attempts: 0,
+            completions: 0,
+            best: None,
+            hints: 0,
+            last_played: String::new()
+        }
+    }
+}
+
+// None for a board without givens
+pub fn key(board: &Board) -> Option<String> {
+    let givens = solver::from_board(board, true);
+    if givens.iter().all(|&v| v == 0) {
+        return None;
+    }
+
+    // FNV-1a
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in solver::to_line(&givens).bytes() {
+        h = (h ^ b as u64).wrapping_mul(0x100000001b3);
+    }
+
+    Some(format!("{:016x}", h))
+}
+
+pub fn stats_path() -> Option<PathBuf> {
+    paths::data_dir().map(|d| d.join("stats.txt"))
+}
+
+// An empty list if nothing has been recorded yet.
+pub fn load() -> io::Result<Stats> {
+    let path = stats_path().ok_or(
+            io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let mut stats = Stats::new();
+
+    let f = match File::open(&path) {
+        Ok(f) => BufReader::new(f),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(stats),
+        Err(e) => return Err(e)
+    };
+
+    for line in f.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.len() != 6 || line.starts_with('#') {
+            continue;
+        }
+
+        let num = |i: usize| words[i].parse::<u32>().unwrap_or(0);
+        stats.insert(String::from(words[0]), PuzzleStats {
+                attempts: num(1),
+                completions: num(2),
+                best: words[3].parse::<u64>().ok(),
+                hints: num(4),
+                last_played: String::from(words[5])
+            });
+    }
+
+    Ok(stats)
+}
+
+fn save(stats: &Stats) -> io::Result<()> {
+    let path = stats_path().ok_or(
+            io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut f = File::create(&path)?;
+    writeln!(f, "# puzzle attempts completions best_ms hints last_played")?;
+
+    for (k, s) in stats.iter() {
+        let best = s.best.map_or(String::from("-"), |ms| format!("{}", ms));
+        writeln!(f, "{} {} {} {} {} {}",
+                k, s.attempts, s.completions, best, s.hints, s.last_played)?;
+    }
+
+    Ok(())
+}
+
+// Applies f to the record of one puzzle, marking it as played today.
+// Returns the new record.
+pub fn update<F>(key: &str, f: F) -> io::Result<PuzzleStats>
+        where F: FnOnce(&mut PuzzleStats) {
+    let mut stats = load()?;
+    let updated = {
+        let s = stats.entry(String::from(key)).or_insert(PuzzleStats::new());
+        f(s);
+        s.last_played = today();
+        s.clone()
+    };
+
+    save(&stats)?;
+    Ok(updated)
+}
+
+fn today() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs()).unwrap_or(0);
+
+    // days since 1970-01-01 to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
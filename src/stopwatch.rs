@@ -0,0 +1,63 @@
+// stopwatch.rs
+
+use std::time::Instant;
+
+pub struct Stopwatch {
+    // milliseconds accumulated before the last pause
+    elapsed: u64,
+
+    // Some(start of the current run) if running
+    running_since: Option<Instant>
+}
+
+impl Stopwatch {
+    pub fn new() -> Stopwatch {
+        Stopwatch {
+            elapsed: 0,
+            running_since: Some(Instant::now())
+        }
+    }
+
+    pub fn with_elapsed(elapsed: u64) -> Stopwatch {
+        Stopwatch {
+            elapsed: elapsed,
+            running_since: Some(Instant::now())
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.running_since.is_none()
+    }
+
+    pub fn pause(&mut self) {
+        self.elapsed = self.elapsed_ms();
+        self.running_since = None;
+    }
+
+    pub fn resume(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        match self.running_since {
+            Some(start) => {
+                let d = start.elapsed();
+                self.elapsed + d.as_secs() * 1000 + (d.subsec_nanos() / 1_000_000) as u64
+            },
+            None => self.elapsed
+        }
+    }
+}
+
+// h:mm:ss, or m:ss under an hour
+pub fn format_ms(ms: u64) -> String {
+    let s = ms / 1000;
+
+    if s >= 3600 {
+        format!("{}:{:02}:{:02}", s / 3600, (s / 60) % 60, s % 60)
+    } else {
+        format!("{}:{:02}", s / 60, s % 60)
+    }
+}
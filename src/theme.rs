@@ -0,0 +1,180 @@
+// theme.rs
+
+use sdl2::pixels::Color;
+
+pub const THEME_NAMES: [&'static str; 3] = ["light", "dark", "high-contrast"];
+
+// Every colour the window draws with.
+#[derive(Clone)]
+pub struct Theme {
+    pub name: &'static str,
+
+    pub background: Color,
+    pub grid: Color,
+    pub grid_block: Color,
+    pub toolbar: Color,
+    pub toolbar_border: Color,
+
+    // tile backgrounds, see Gui::draw_tile
+    pub chequer: Color,
+    pub wrong: Color,
+    pub selected: Color,
+    pub has_value: Color,
+    pub has_candidate: Color,
+    pub peer: Color,
+
+    pub candidate: Color,
+
+    // a candidate that is the only place left for its value
+    pub candidate_unique: Color,
+
+    pub cursor: Color,
+    pub link: Color,
+
+    // text and outlines, and the panels behind text
+    pub text: Color,
+    pub panel: Color,
+    pub timer_paused: Color,
+
+    // colours 1 to NUM_COLOURS of the colour brush
+    pub palette: [Color; 6],
+
+    // multiplied into the toolbar sprites
+    pub sprite_tint: Color,
+
+    // the colours of the (init, guess, conflict) tile numbers
+    pub numbers: (Color, Color, Color),
+
+    // draw the number sprites in the colours above, rather than their own
+    pub tint_numbers: bool,
+
+    // underline conflicting numbers, so they do not depend on colour alone
+    pub mark_conflicts: bool
+}
+
+pub fn by_name(name: &str) -> Option<Theme> {
+    match name {
+        "light" => Some(light()),
+        "dark" => Some(dark()),
+        "high-contrast" => Some(high_contrast()),
+        _ => None
+    }
+}
+
+// The theme after name, wrapping around.
+pub fn next(name: &str) -> Theme {
+    let i = THEME_NAMES.iter().position(|&n| n == name).map_or(0, |i| i + 1);
+    by_name(THEME_NAMES[i % THEME_NAMES.len()]).unwrap()
+}
+
+pub fn light() -> Theme {
+    Theme {
+        name: "light",
+        background: Color::RGB(0xD0, 0xD0, 0xD0),
+        grid: Color::RGB(0x98, 0x98, 0x98),
+        grid_block: Color::RGB(0x58, 0x58, 0x58),
+        toolbar: Color::RGB(0x98, 0x98, 0x98),
+        toolbar_border: Color::RGB(0x58, 0x58, 0x58),
+        chequer: Color::RGB(0xC2, 0xBC, 0xBC),
+        wrong: Color::RGB(0xE0, 0x88, 0x88),
+        selected: Color::RGB(0xA8, 0xB8, 0xD8),
+        has_value: Color::RGB(0xA0, 0xC8, 0xA0),
+        has_candidate: Color::RGB(0xC0, 0xD8, 0xC0),
+        peer: Color::RGB(0xD8, 0xD4, 0xB0),
+        candidate: Color::RGB(0x98, 0x98, 0x98),
+        candidate_unique: Color::RGB(0x58, 0x58, 0x58),
+        cursor: Color::RGB(0x58, 0x58, 0x58),
+        link: Color::RGB(0x30, 0x50, 0x90),
+        text: Color::RGB(0x58, 0x58, 0x58),
+        panel: Color::RGB(0xD0, 0xD0, 0xD0),
+        timer_paused: Color::RGB(0xD0, 0xD0, 0xD0),
+
+        // after Okabe and Ito
+        palette: [
+            Color::RGB(0xE6, 0x9F, 0x00), Color::RGB(0x56, 0xB4, 0xE9),
+            Color::RGB(0x00, 0x9E, 0x73), Color::RGB(0xF0, 0xE4, 0x42),
+            Color::RGB(0xCC, 0x79, 0xA7), Color::RGB(0xD5, 0x5E, 0x00)],
+
+        sprite_tint: Color::RGB(0xFF, 0xFF, 0xFF),
+
+        // for numbers not drawn with the sprites
+        numbers: (
+            Color::RGB(0x40, 0x40, 0x40),
+            Color::RGB(0x30, 0x50, 0x90),
+            Color::RGB(0xC0, 0x20, 0x20)),
+        tint_numbers: false,
+        mark_conflicts: false
+    }
+}
+
+pub fn dark() -> Theme {
+    Theme {
+        name: "dark",
+        background: Color::RGB(0x20, 0x24, 0x28),
+        grid: Color::RGB(0x3A, 0x3F, 0x45),
+        grid_block: Color::RGB(0x6A, 0x70, 0x78),
+        toolbar: Color::RGB(0x30, 0x34, 0x3A),
+        toolbar_border: Color::RGB(0x6A, 0x70, 0x78),
+        chequer: Color::RGB(0x2A, 0x2E, 0x33),
+        wrong: Color::RGB(0x6A, 0x2E, 0x2E),
+        selected: Color::RGB(0x2E, 0x3F, 0x5E),
+        has_value: Color::RGB(0x2E, 0x4A, 0x2E),
+        has_candidate: Color::RGB(0x26, 0x38, 0x26),
+        peer: Color::RGB(0x3A, 0x38, 0x28),
+        candidate: Color::RGB(0x6A, 0x70, 0x78),
+        candidate_unique: Color::RGB(0xB0, 0xB8, 0xC0),
+        cursor: Color::RGB(0xD0, 0xD8, 0xE0),
+        link: Color::RGB(0x7F, 0xA8, 0xE0),
+        text: Color::RGB(0xD0, 0xD8, 0xE0),
+        panel: Color::RGB(0x20, 0x24, 0x28),
+        timer_paused: Color::RGB(0x4A, 0x4F, 0x55),
+
+        // the light palette, darkened to sit behind light numbers
+        palette: [
+            Color::RGB(0x8A, 0x60, 0x00), Color::RGB(0x2E, 0x6A, 0x8A),
+            Color::RGB(0x00, 0x5E, 0x45), Color::RGB(0x8A, 0x84, 0x26),
+            Color::RGB(0x7A, 0x48, 0x64), Color::RGB(0x80, 0x38, 0x00)],
+
+        sprite_tint: Color::RGB(0x90, 0x90, 0x98),
+        numbers: (
+            Color::RGB(0xE0, 0xE4, 0xE8),
+            Color::RGB(0x7F, 0xB0, 0xFF),
+            Color::RGB(0xFF, 0x70, 0x70)),
+        tint_numbers: true,
+        mark_conflicts: false
+    }
+}
+
+// Black and white, with guesses in blue and conflicts in vermillion and
+// underlined, which stay apart with any kind of colour blindness.
+pub fn high_contrast() -> Theme {
+    Theme {
+        name: "high-contrast",
+        background: Color::RGB(0xFF, 0xFF, 0xFF),
+        grid: Color::RGB(0x80, 0x80, 0x80),
+        grid_block: Color::RGB(0x00, 0x00, 0x00),
+        toolbar: Color::RGB(0xC0, 0xC0, 0xC0),
+        toolbar_border: Color::RGB(0x00, 0x00, 0x00),
+        chequer: Color::RGB(0xE8, 0xE8, 0xE8),
+        wrong: Color::RGB(0xF0, 0xC8, 0x98),
+        selected: Color::RGB(0x9B, 0xC8, 0xF0),
+        has_value: Color::RGB(0xC8, 0xE6, 0xD8),
+        has_candidate: Color::RGB(0xE0, 0xF0, 0xE8),
+        peer: Color::RGB(0xF4, 0xF0, 0xC8),
+        candidate: Color::RGB(0x70, 0x70, 0x70),
+        candidate_unique: Color::RGB(0x00, 0x00, 0x00),
+        cursor: Color::RGB(0x00, 0x00, 0x00),
+        link: Color::RGB(0x00, 0x72, 0xB2),
+        text: Color::RGB(0x00, 0x00, 0x00),
+        panel: Color::RGB(0xFF, 0xFF, 0xFF),
+        timer_paused: Color::RGB(0xE0, 0xE0, 0xE0),
+        palette: light().palette,
+        sprite_tint: Color::RGB(0xFF, 0xFF, 0xFF),
+        numbers: (
+            Color::RGB(0x00, 0x00, 0x00),
+            Color::RGB(0x00, 0x72, 0xB2),
+            Color::RGB(0xD5, 0x5E, 0x00)),
+        tint_numbers: true,
+        mark_conflicts: true
+    }
+}
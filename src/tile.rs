@@ -1,5 +1,6 @@
 // tile.rs
 
+#[derive(Clone)]
 pub struct Tile {
     pub x: u8,
     pub y: u8,
@@ -118,6 +119,32 @@ impl Tile {
         }
     }
 
+    pub fn is_valid_restore_value(&self, x: u8, y: u8, value: u8) -> bool {
+        return x < 9 && y < 9 && (1 <= value && value <= 9)
+            && (self.x != x
+                || self.y != y
+                || self.eliminated.iter().any(|&v| v == value))
+    }
+
+    // undoes cross_out_value
+    pub fn restore_value(&self, x: u8, y: u8, value: u8) -> Tile {
+        assert!(x < 9 && y < 9 && (1 <= value && value <= 9));
+        let mut vs = self.eliminated.clone();
+
+        if self.x == x && self.y == y {
+            vs.retain(|&v| v != value);
+        }
+
+        Tile {
+            x: self.x,
+            y: self.y,
+            init: self.init,
+            assignment: self.assignment,
+            candidates: self.candidates.clone(),
+            eliminated: vs
+        }
+    }
+
     pub fn is_init(&self) -> bool {
         self.init
     }
@@ -0,0 +1,138 @@
+// ttf.rs
+
+// Text in a TrueType font through SDL_ttf, for the numbers and text drawn
+// with the sprites and pixel fonts otherwise.  The printable ASCII
+// characters are rendered in white once for each size, and tinted as they
+// are drawn.
+
+use std::path::Path;
+use sdl2::pixels::Color;
+use sdl2::render::Renderer;
+
+#[cfg(feature = "ttf")]
+use std::collections::HashMap;
+#[cfg(feature = "ttf")]
+use std::path::PathBuf;
+#[cfg(feature = "ttf")]
+use sdl2::rect::Rect;
+#[cfg(feature = "ttf")]
+use sdl2::render::Texture;
+#[cfg(feature = "ttf")]
+use sdl2::ttf;
+#[cfg(feature = "ttf")]
+use sdl2::ttf::Sdl2TtfContext;
+
+#[cfg(feature = "ttf")]
+pub struct TtfFont {
+    context: Sdl2TtfContext,
+    path: PathBuf,
+
+    // glyphs by line height in pixels, then character
+    sizes: HashMap<u32, HashMap<char, Glyph>>
+}
+
+#[cfg(feature = "ttf")]
+struct Glyph {
+    texture: Texture,
+
+    // the rows from the top of the texture to the top of the glyph, and
+    // the height of the glyph itself
+    ink_y: i32,
+    ink_h: i32
+}
+
+// Without the ttf feature, there is never a font.
+#[cfg(not(feature = "ttf"))]
+pub enum TtfFont {}
+
+#[cfg(feature = "ttf")]
+impl TtfFont {
+    pub fn load(path: &Path) -> Option<TtfFont> {
+        let context = match ttf::init() {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("SDL_ttf: {}", e);
+                return None;
+            }
+        };
+
+        if let Err(e) = context.load_font(path, 12) {
+            eprintln!("{}: {}", path.display(), e);
+            return None;
+        }
+
+        Some(TtfFont {
+            context: context,
+            path: path.to_path_buf(),
+            sizes: HashMap::new()
+        })
+    }
+
+    // Draws c in a line height pixels high, with the glyph centred on
+    // (x, y).
+    pub fn draw_char(&mut self, renderer: &mut Renderer,
+            c: char, (x, y): (i32, i32), height: u32, colour: Color) {
+        if !self.sizes.contains_key(&height) {
+            // forget the sizes of earlier window sizes
+            if self.sizes.len() >= 8 {
+                self.sizes.clear();
+            }
+            let glyphs = self.render_glyphs(renderer, height);
+            self.sizes.insert(height, glyphs);
+        }
+
+        if let Some(g) = self.sizes.get_mut(&height).and_then(|glyphs| glyphs.get_mut(&c)) {
+            let q = g.texture.query();
+            let (r, gr, b) = colour.rgb();
+            g.texture.set_color_mod(r, gr, b);
+            let _ = renderer.copy(&g.texture, None, Some(Rect::new(
+                    x - (q.width / 2) as i32,
+                    y - g.ink_y - g.ink_h / 2,
+                    q.width, q.height)));
+        }
+    }
+
+    fn render_glyphs(&self, renderer: &Renderer, height: u32) -> HashMap<char, Glyph> {
+        let mut glyphs = HashMap::new();
+
+        // the point size with the line height nearest to height
+        let point_size = match self.context.load_font(&self.path, height as u16) {
+            Ok(font) => height * height / (font.height().max(1) as u32),
+            Err(_) => return glyphs
+        };
+        let font = match self.context.load_font(&self.path, point_size.max(1) as u16) {
+            Ok(font) => font,
+            Err(_) => return glyphs
+        };
+
+        for c in (33..127u8).map(|b| b as char) {
+            let metrics = match font.find_glyph_metrics(c) {
+                Some(m) => m,
+                None => continue
+            };
+
+            let surface = font.render_char(c).blended(Color::RGB(0xFF, 0xFF, 0xFF));
+            if let Ok(texture) = surface.map_err(|_| ()).and_then(
+                    |s| renderer.create_texture_from_surface(&s).map_err(|_| ())) {
+                glyphs.insert(c, Glyph {
+                    texture: texture,
+                    ink_y: font.ascent() - metrics.maxy,
+                    ink_h: metrics.maxy - metrics.miny
+                });
+            }
+        }
+
+        glyphs
+    }
+}
+
+#[cfg(not(feature = "ttf"))]
+impl TtfFont {
+    pub fn load(_: &Path) -> Option<TtfFont> {
+        None
+    }
+
+    pub fn draw_char(&mut self, _: &mut Renderer, _: char, _: (i32, i32), _: u32, _: Color) {
+        match *self {}
+    }
+}
@@ -0,0 +1,539 @@
+// tui.rs
+
+use std::cmp::{max,min};
+use std::io;
+use std::io::{Stdout,Write};
+use std::thread;
+use std::time::{Duration,Instant};
+use termion::{AsyncReader,async_stdin,clear,color,cursor,style,terminal_size};
+use termion::event::Key;
+use termion::input::{Keys,TermRead};
+use termion::raw::{IntoRawMode,RawTerminal};
+use termion::screen::{AlternateScreen,IntoAlternateScreen};
+
+use action::SudokuAction;
+use board::{Board,NUM_COLOURS};
+use frontend::{Brush,Frontend};
+use game::GameStatus;
+use settings::Settings;
+use stopwatch::format_ms;
+use tile::Tile;
+
+// colours 1 to NUM_COLOURS as xterm-256 colours, as close as possible to
+// the palette of the window
+const PALETTE: [u8; 6] = [214, 117, 36, 227, 175, 166];
+
+// shift+1 to shift+9
+const SHIFTED_DIGITS: &'static str = "!@#$%^&*(";
+
+// rows needed to draw the candidates of every tile
+const FULL_BOARD_HEIGHT: u16 = 9 * 3 + 10 + 8;
+
+pub struct Tui {
+    keys: Keys<AsyncReader>,
+    out: AlternateScreen<RawTerminal<Stdout>>,
+    state: TuiState,
+    status: Option<GameStatus>,
+    last_redraw: Instant,
+
+    // as loaded, written back if the brush is changed
+    settings: Settings,
+
+    // exported text, shown until the next key press
+    message: String
+}
+
+struct TuiState {
+    selected_brush: Brush,
+    selected_value: u8,
+    selected_colour: u8,
+    cursor: (u8,u8),
+
+    // the kind of link drawn by the link brush
+    link_strong: bool,
+
+    // Some(candidate) at one end of a link, until the other end is picked
+    link_start: Option<(u8,u8,u8)>,
+
+    // tiles for bulk actions
+    selection: Vec<(u8,u8)>,
+
+    // Some(puzzle number typed so far) while jumping to a puzzle
+    goto_puzzle: Option<usize>,
+
+    show_stats: bool
+}
+
+impl Tui {
+    pub fn new(settings: Settings) -> io::Result<Tui> {
+        let out = io::stdout().into_raw_mode()?.into_alternate_screen()?;
+
+        let mut tui = Tui {
+            keys: async_stdin().keys(),
+            out: out,
+            state: TuiState::new(&settings),
+            status: None,
+            last_redraw: Instant::now(),
+            settings: settings,
+            message: String::new()
+        };
+
+        write!(tui.out, "{}", cursor::Hide)?;
+        Ok(tui)
+    }
+
+    fn draw_board(&self, s: &mut String, board: &Board, full: bool) {
+        let w = if full { 5 } else { 3 };
+        let h = if full { 3 } else { 1 };
+        let paused = self.status.as_ref().map_or(false, |st| st.paused);
+
+        for y in 0..9 {
+            s.push_str(&Tui::border(y, w));
+
+            for row in 0..h {
+                for x in 0..9 {
+                    s.push_str(if x % 3 == 0 { "┃" } else { "│" });
+                    if paused {
+                        // hide the board while paused
+                        s.push_str(&" ".repeat(w));
+                    } else if let Some(t) = board.get(x, y) {
+                        self.draw_tile(s, board, t, row, full);
+                    }
+                }
+                s.push_str("┃\r\n");
+            }
+        }
+
+        s.push_str(&Tui::border(9, w));
+    }
+
+    // the horizontal line above row y
+    fn border(y: u8, w: usize) -> String {
+        let (l, m, b, r, line) = match y {
+            0 => ("┏", "┯", "┳", "┓", "━"),
+            9 => ("┗", "┷", "┻", "┛", "━"),
+            3 | 6 => ("┣", "┿", "╋", "┫", "━"),
+            _ => ("┠", "┼", "╂", "┨", "─")
+        };
+
+        let mut s = String::from(l);
+        for x in 0..9 {
+            if x > 0 {
+                s.push_str(if x % 3 == 0 { b } else { m });
+            }
+            for _ in 0..w {
+                s.push_str(line);
+            }
+        }
+        s.push_str(r);
+        s.push_str("\r\n");
+        s
+    }
+
+    // Draws one row of a tile.  Full tiles are three rows high, with the
+    // candidates laid out like the number pad.
+    fn draw_tile(&self, s: &mut String, board: &Board, tile: &Tile, row: u8, full: bool) {
+        let is_cursor = self.state.cursor == (tile.x, tile.y);
+        let is_selected = self.state.selection.iter().any(|&t| t == (tile.x, tile.y));
+        if is_cursor {
+            s.push_str(&format!("{}", style::Invert));
+        }
+        let colour = board.tile_colour(tile.x, tile.y);
+        if is_selected {
+            s.push_str(&format!("{}", color::Bg(color::Yellow)));
+        } else if colour != 0 {
+            s.push_str(&format!("{}", color::Bg(palette_colour(colour))));
+        }
+
+        let wrong = self.status.as_ref().map_or(false,
+                |st| st.mistakes.iter().any(|&m| m == (tile.x, tile.y)));
+
+        s.push(' ');
+
+        if let Some(v) = tile.assignment {
+            let pad = if full { " " } else { "" };
+            if full && row != 1 {
+                s.push_str("   ");
+            } else if tile.is_init() {
+                s.push_str(&format!("{}{}{}{}{}", pad, style::Bold, v, style::NoBold, pad));
+            } else if wrong {
+                s.push_str(&format!("{}{}{}{}{}{}{}",
+                        pad, style::Underline, color::Fg(color::Magenta), v,
+                        color::Fg(color::Reset), style::NoUnderline, pad));
+            } else if tile.is_guess() {
+                s.push_str(&format!("{}{}{}{}{}",
+                        pad, color::Fg(color::Blue), v, color::Fg(color::Reset), pad));
+            } else {
+                s.push_str(&format!("{}{}{}{}{}{}{}",
+                        pad, style::Bold, color::Fg(color::Red), v,
+                        color::Fg(color::Reset), style::NoBold, pad));
+            }
+        } else if !full {
+            s.push('.');
+        } else {
+            for x in 0..3 {
+                let v = 3 * (2 - row) + x + 1;
+                let remaining = tile.candidates.iter().any(|&v1| v1 == v)
+                    && tile.eliminated.iter().all(|&v2| v2 != v);
+
+                let c = board.candidate_colour(tile.x, tile.y, v);
+
+                if !remaining {
+                    s.push(' ');
+                } else if c != 0 {
+                    s.push_str(&format!("{}{}{}{}{}",
+                            style::Bold, color::Fg(palette_colour(c)), v,
+                            color::Fg(color::Reset), style::NoBold));
+                } else if !self.settings.assisted_candidates || board.is_unique(tile, v) {
+                    s.push_str(&format!("{}", v));
+                } else {
+                    s.push_str(&format!("{}{}{}",
+                            color::Fg(color::LightBlack), v, color::Fg(color::Reset)));
+                }
+            }
+        }
+
+        s.push(' ');
+
+        if is_selected || colour != 0 {
+            s.push_str(&format!("{}", color::Bg(color::Reset)));
+        }
+        if is_cursor {
+            s.push_str(&format!("{}", style::NoInvert));
+        }
+    }
+
+    fn draw_status(&self, s: &mut String) {
+        let brush = match self.state.selected_brush {
+            Brush::Pencil => String::from("pencil"),
+            Brush::CrossOut => String::from("cross out"),
+            Brush::Colour => format!("colour {}{}  {}",
+                    color::Bg(palette_colour(self.state.selected_colour)),
+                    self.state.selected_colour, color::Bg(color::Reset)),
+            Brush::Link => {
+                let kind = if self.state.link_strong { "strong" } else { "weak" };
+                match self.state.link_start {
+                    Some(c) => format!("{} link from {}", kind, candidate_name(c)),
+                    None => format!("{} link", kind)
+                }
+            }
+        };
+
+        s.push_str(&format!(" brush: {}   value: {}", brush, self.state.selected_value));
+
+        if let Some(ref st) = self.status {
+            s.push_str(&format!("   time: {}", format_ms(st.elapsed)));
+            if st.paused {
+                s.push_str(" (paused)");
+            }
+            if st.check_mode {
+                s.push_str("   checking");
+            }
+
+            if let Some((n, count)) = st.puzzle_position {
+                if count > 1 {
+                    s.push_str(&format!("   puzzle: {} / {}", n + 1, count));
+                }
+            }
+        }
+
+        match self.state.goto_puzzle {
+            Some(0) => s.push_str("   go to puzzle: _"),
+            Some(n) => s.push_str(&format!("   go to puzzle: {}_", n)),
+            None => {}
+        }
+
+        s.push_str("\r\n");
+
+        if let Some(c) = self.status.as_ref().and_then(|st| st.completion) {
+            s.push_str(&format!(
+                    " {}Solved in {}{} with {} undo(s) and {} auto-fill(s).  \
+                    Press enter to continue, or n for a new game.\r\n",
+                    style::Bold, format_ms(c.elapsed), style::NoBold,
+                    c.undos, c.autofills));
+        }
+
+        if self.state.show_stats {
+            match self.status.as_ref().and_then(|st| st.stats.as_ref()) {
+                Some(ps) => s.push_str(&format!(
+                        " attempts: {}   completions: {}   best: {}   hints: {}   \
+                        last played: {}\r\n",
+                        ps.attempts, ps.completions,
+                        ps.best.map_or(String::from("-"), format_ms),
+                        ps.hints, ps.last_played)),
+                None => s.push_str(" no statistics for this puzzle\r\n")
+            }
+        }
+
+        s.push_str(" arrows/hjkl move, shift+arrows/HJKL select, esc deselect, del clear,\r\n");
+        s.push_str(" 1-9 assign, shift+1-9 cross out, enter/space apply brush, -/+ value,\r\n");
+        s.push_str(" c/v/b/y brush, z/x undo/redo, a auto-fill, w check, m check mode, r rewind,\r\n");
+        s.push_str(" p pause, ^s/^l save/load, e export, i statistics, n new, g go to, q quit\r\n");
+    }
+
+    // Links in Eureka notation, = for strong and - for weak, cut short to
+    // fit on one line.
+    fn draw_links(&self, s: &mut String, board: &Board, width: usize) {
+        if board.links().is_empty() {
+            return;
+        }
+
+        let mut line = String::from(" links:");
+        for l in board.links().iter() {
+            let link = format!("  {}{}{}", candidate_name(l.from),
+                    if l.strong { "=" } else { "-" }, candidate_name(l.to));
+            if line.len() + link.len() + 5 > width {
+                line.push_str("  ...");
+                break;
+            }
+            line.push_str(&link);
+        }
+
+        s.push_str(&line);
+        s.push_str("\r\n");
+    }
+}
+
+impl Frontend for Tui {
+    fn read_input(&mut self) -> SudokuAction {
+        // redraw every second to update the timer
+        while self.last_redraw.elapsed() < Duration::from_secs(1) {
+            if let Some(Ok(k)) = self.keys.next() {
+                self.message.clear();
+
+                let solved = self.status.as_ref().map_or(false, |st| st.completion.is_some());
+                if solved && (k == Key::Char('\n') || k == Key::Esc) {
+                    return SudokuAction::Continue;
+                }
+
+                return self.state.on_key_down(k);
+            }
+
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        SudokuAction::NoOp
+    }
+
+    fn present(&mut self, board: &Board) {
+        let (term_w, term_h) = terminal_size().unwrap_or((80, 24));
+        let full = term_h >= FULL_BOARD_HEIGHT;
+        let mut s = format!("{}{}", clear::All, cursor::Goto(1, 1));
+        self.last_redraw = Instant::now();
+
+        self.draw_board(&mut s, board, full);
+        self.draw_links(&mut s, board, term_w as usize);
+        self.draw_status(&mut s);
+
+        if !self.message.is_empty() {
+            s.push_str("\r\n");
+            s.push_str(&self.message.replace("\n", "\r\n"));
+        }
+
+        let _ = self.out.write_all(s.as_bytes());
+        let _ = self.out.flush();
+    }
+
+    fn set_status(&mut self, status: &GameStatus) {
+        self.status = Some(status.clone());
+    }
+
+    fn print(&mut self, text: &str) {
+        self.message.push_str(text);
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        let _ = write!(self.out, "{}", cursor::Show);
+        let _ = self.out.flush();
+
+        if self.state.selected_brush != self.settings.brush {
+            self.settings.brush = self.state.selected_brush;
+            if let Err(e) = self.settings.save() {
+                eprintln!("settings: {}", e);
+            }
+        }
+    }
+}
+
+impl TuiState {
+    fn new(settings: &Settings) -> TuiState {
+        TuiState {
+            selected_brush: settings.brush,
+            selected_value: 1,
+            selected_colour: 1,
+            cursor: (4, 4),
+            link_strong: true,
+            link_start: None,
+            selection: Vec::new(),
+            goto_puzzle: None,
+            show_stats: false
+        }
+    }
+
+    fn on_key_down(&mut self, key: Key) -> SudokuAction {
+        if let Some(n) = self.goto_puzzle {
+            return self.on_goto_puzzle_key_down(key, n);
+        }
+
+        let (x, y) = self.cursor;
+
+        match key {
+            Key::Char('q') | Key::Ctrl('c') => return SudokuAction::Quit,
+
+            Key::Char('n') => return SudokuAction::New(None),
+            Key::PageDown => return SudokuAction::NextPuzzle,
+            Key::PageUp => return SudokuAction::PrevPuzzle,
+            Key::Char('g') => self.goto_puzzle = Some(0),
+            Key::Char('z') => return SudokuAction::Undo,
+            Key::Char('x') => return SudokuAction::Redo,
+
+            Key::Char('c') => self.selected_brush = Brush::Pencil,
+            Key::Char('v') => self.selected_brush = Brush::CrossOut,
+
+            // the colour brush, or the next colour if already selected
+            Key::Char('B') => return SudokuAction::ClearColours,
+            Key::Char('b') =>
+                if self.selected_brush == Brush::Colour {
+                    self.selected_colour = self.selected_colour % NUM_COLOURS + 1;
+                } else {
+                    self.selected_brush = Brush::Colour;
+                },
+
+            // the link brush, or the other kind of link if already selected
+            Key::Char('Y') => return SudokuAction::ClearLinks,
+            Key::Char('y') =>
+                if self.selected_brush == Brush::Link {
+                    self.link_strong = !self.link_strong;
+                } else {
+                    self.selected_brush = Brush::Link;
+                },
+
+            Key::Char('a') => return SudokuAction::AutoFill,
+            Key::Char('w') => return SudokuAction::CheckNow,
+            Key::Char('m') => return SudokuAction::ToggleCheckMode,
+            Key::Char('r') => return SudokuAction::RewindToCorrect,
+            Key::Char('p') => return SudokuAction::TogglePause,
+            Key::Char('i') => self.show_stats = !self.show_stats,
+            Key::Ctrl('s') => return SudokuAction::SaveGame,
+            Key::Ctrl('l') => return SudokuAction::LoadGame,
+            Key::Char('e') => return SudokuAction::Export,
+
+            // digits select a value and assign it at the cursor, shifted
+            // digits (on a US layout) cross it out or restore it
+            Key::Char(c) if '1' <= c && c <= '9' => {
+                self.selected_value = c.to_digit(10).unwrap() as u8;
+                return SudokuAction::AssignValue(x, y, self.selected_value);
+            },
+
+            Key::Char(c) if SHIFTED_DIGITS.contains(c) => {
+                self.selected_value = (SHIFTED_DIGITS.find(c).unwrap() + 1) as u8;
+                if !self.selection.is_empty() {
+                    return SudokuAction::ToggleCrossOutMany(
+                            self.selection.clone(), self.selected_value);
+                }
+                return SudokuAction::ToggleCrossOut(x, y, self.selected_value);
+            },
+
+            Key::Char('-') => self.on_wheel(-1),
+            Key::Char('+') | Key::Char('=') => self.on_wheel(1),
+
+            Key::Left | Key::Char('h') => self.cursor = (max(x, 1) - 1, y),
+            Key::Right | Key::Char('l') => self.cursor = (min(x + 1, 8), y),
+            Key::Up | Key::Char('k') => self.cursor = (x, max(y, 1) - 1),
+            Key::Down | Key::Char('j') => self.cursor = (x, min(y + 1, 8)),
+
+            // shift+move selects the tiles passed over
+            Key::ShiftLeft | Key::Char('H') => self.select_to((max(x, 1) - 1, y)),
+            Key::ShiftRight | Key::Char('L') => self.select_to((min(x + 1, 8), y)),
+            Key::ShiftUp | Key::Char('K') => self.select_to((x, max(y, 1) - 1)),
+            Key::ShiftDown | Key::Char('J') => self.select_to((x, min(y + 1, 8))),
+            Key::Esc =>
+                if self.link_start.is_some() {
+                    self.link_start = None;
+                } else {
+                    self.selection.clear();
+                },
+
+            Key::Char('\n') | Key::Char(' ') =>
+                match self.selected_brush {
+                    Brush::CrossOut if !self.selection.is_empty() =>
+                        return SudokuAction::CrossOutMany(
+                                self.selection.clone(), self.selected_value),
+                    Brush::Pencil => return SudokuAction::AssignValue(
+                            x, y, self.selected_value),
+                    Brush::CrossOut => return SudokuAction::CrossOutValue(
+                            x, y, self.selected_value),
+                    Brush::Colour => return SudokuAction::ColourTile(
+                            x, y, self.selected_colour),
+
+                    // the first press picks one end of the link, the
+                    // second the other
+                    Brush::Link =>
+                        match self.link_start.take() {
+                            Some(start) if (start.0, start.1) != (x, y) =>
+                                return SudokuAction::ToggleLink(
+                                        start, (x, y, self.selected_value), self.link_strong),
+                            _ => self.link_start = Some((x, y, self.selected_value))
+                        }
+                },
+
+            Key::Backspace | Key::Delete =>
+                if !self.selection.is_empty() {
+                    return SudokuAction::UnassignMany(self.selection.clone());
+                } else {
+                    return SudokuAction::UnassignValue(x, y);
+                },
+
+            _ => {}
+        }
+
+        SudokuAction::NoOp
+    }
+
+    fn select_to(&mut self, xy: (u8,u8)) {
+        for &t in [self.cursor, xy].iter() {
+            if !self.selection.contains(&t) {
+                self.selection.push(t);
+            }
+        }
+        self.cursor = xy;
+    }
+
+    fn on_goto_puzzle_key_down(&mut self, key: Key, n: usize) -> SudokuAction {
+        match key {
+            Key::Char(c) if '0' <= c && c <= '9' => {
+                let d = c.to_digit(10).unwrap() as usize;
+                self.goto_puzzle = Some(n.saturating_mul(10).saturating_add(d));
+            },
+
+            Key::Backspace => self.goto_puzzle = Some(n / 10),
+            Key::Esc => self.goto_puzzle = None,
+
+            Key::Char('\n') => {
+                self.goto_puzzle = None;
+                if n > 0 {
+                    return SudokuAction::GotoPuzzle(n - 1);
+                }
+            },
+
+            _ => {}
+        }
+
+        SudokuAction::NoOp
+    }
+
+    fn on_wheel(&mut self, delta: i32) {
+        self.selected_value = max(1, min(self.selected_value as i32 + delta, 9)) as u8;
+    }
+}
+
+// (v)rYcX, counting from 1
+fn candidate_name((x, y, v): (u8,u8,u8)) -> String {
+    format!("({})r{}c{}", v, y + 1, x + 1)
+}
+
+fn palette_colour(c: u8) -> color::AnsiValue {
+    color::AnsiValue(PALETTE[(max(c, 1) - 1) as usize % PALETTE.len()])
+}